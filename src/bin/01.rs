@@ -1,22 +1,124 @@
-use advent_of_code::helpers::Input;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Lines},
+};
+
+use advent_of_code::helpers::{Input, TopK};
 use anyhow::{Context, Result};
-use itertools::Itertools;
 
-pub fn part_one(input: Input) -> Result<u32> {
-    get_sums(input).max().context("max not found")
+pub fn part_one(input: Input) -> Result<u64> {
+    Ok(top_elves(input.as_bytes(), 1)?
+        .first()
+        .context("max not found")?
+        .calories)
+}
+
+pub fn part_two(input: Input) -> Result<u64> {
+    Ok(top_elves(input.as_bytes(), 3)?
+        .iter()
+        .map(|e| e.calories)
+        .sum())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elf {
+    /// 1-based position of the elf in the input.
+    index: usize,
+    calories: u64,
+}
+
+/// Streams the input and returns the `k` elves carrying the most calories, most first.
+/// Ties are broken in favor of the elf listed first.
+pub fn top_elves(reader: impl BufRead, k: usize) -> Result<Vec<Elf>> {
+    let mut top = TopK::by(k, |a: &Elf, b: &Elf| {
+        a.calories.cmp(&b.calories).then(b.index.cmp(&a.index))
+    });
+    for elf in ElfTotals::new(reader) {
+        top.push(elf?);
+    }
+    Ok(top.into_sorted_vec())
+}
+
+/// Iterator over the calorie totals of each elf, reading one line at a time.
+struct ElfTotals<R> {
+    lines: Lines<R>,
+    line_no: usize,
+    index: usize,
+}
+
+impl<R: BufRead> ElfTotals<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line_no: 0,
+            index: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ElfTotals<R> {
+    type Item = Result<Elf>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut calories = None;
+        for line in self.lines.by_ref() {
+            self.line_no += 1;
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            if line.trim().is_empty() {
+                if calories.is_some() {
+                    break;
+                }
+                continue;
+            }
+
+            match line.trim().parse::<u64>() {
+                Ok(c) => *calories.get_or_insert(0) += c,
+                Err(e) => {
+                    return Some(Err(e).with_context(|| {
+                        format!("line {}: invalid calories {:?}", self.line_no, line)
+                    }))
+                }
+            }
+        }
+
+        calories.map(|calories| {
+            self.index += 1;
+            Ok(Elf {
+                index: self.index,
+                calories,
+            })
+        })
+    }
 }
 
-pub fn part_two(input: Input) -> Result<u32> {
-    Ok(get_sums(input).sorted().rev().take(3).sum())
+struct Args {
+    stream: Option<String>,
+    top: usize,
 }
 
-pub fn get_sums(input: Input) -> impl Iterator<Item = u32> + '_ {
-    input
-        .group_and_tform_lines(|l| l.parse::<u32>().unwrap())
-        .map(|g| g.sum())
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        stream: args.opt_value_from_str("--stream")?,
+        top: args.opt_value_from_str("--top")?.unwrap_or(3),
+    })
 }
 
 fn main() {
+    let args = parse_args().expect("invalid arguments");
+
+    // `--stream <file>` reads an arbitrarily large input without loading it into memory.
+    if let Some(path) = args.stream {
+        let file = File::open(&path).expect("could not open input file");
+        for elf in top_elves(BufReader::new(file), args.top).unwrap() {
+            println!("elf #{}: {}", elf.index, elf.calories);
+        }
+        return;
+    }
+
     let input = &advent_of_code::read_file("inputs", 1);
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
@@ -37,4 +139,24 @@ mod tests {
         let input = advent_of_code::read_file("examples", 1);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 45000);
     }
+
+    #[test]
+    fn test_top_elves() {
+        let input = advent_of_code::read_file("examples", 1);
+        let top = top_elves(input.as_bytes(), 2).unwrap();
+        assert_eq!(
+            top,
+            vec![
+                Elf {
+                    index: 4,
+                    calories: 24000
+                },
+                Elf {
+                    index: 3,
+                    calories: 11000
+                }
+            ]
+        );
+        assert!(top_elves("1\nx\n".as_bytes(), 1).is_err());
+    }
 }
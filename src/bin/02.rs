@@ -1,48 +1,168 @@
 use advent_of_code::helpers::Input;
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use itertools::Itertools;
-use std::collections::HashMap;
 
 pub fn part_one(input: Input) -> Result<u32> {
-    let score_map: HashMap<(&str, &str), u32> = HashMap::from([
-        (("A", "X"), 4),
-        (("A", "Y"), 8),
-        (("A", "Z"), 3),
-        (("B", "X"), 1),
-        (("B", "Y"), 5),
-        (("B", "Z"), 9),
-        (("C", "X"), 7),
-        (("C", "Y"), 2),
-        (("C", "Z"), 6),
-    ]);
-
-    Ok(score(input, &score_map))
+    let game = Game::rock_paper_scissors();
+    game.score(input, &Guide::shapes("ABC", "XYZ"))
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
-    let score_map: HashMap<(&str, &str), u32> = HashMap::from([
-        (("A", "X"), 3),
-        (("A", "Y"), 4),
-        (("A", "Z"), 8),
-        (("B", "X"), 1),
-        (("B", "Y"), 5),
-        (("B", "Z"), 9),
-        (("C", "X"), 2),
-        (("C", "Y"), 6),
-        (("C", "Z"), 7),
-    ]);
-
-    Ok(score(input, &score_map))
+    let game = Game::rock_paper_scissors();
+    game.score(input, &Guide::outcomes("ABC", "XYZ")?)
 }
 
-fn score(input: Input, rules: &HashMap<(&str, &str), u32>) -> u32 {
-    input
-        .split_and_tform_lines(|l| *rules.get(&parse_move(l)).unwrap())
-        .sum()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Shape(usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Lose,
+    Draw,
+    Win,
+}
+
+impl Outcome {
+    const ALL: [Outcome; 3] = [Outcome::Lose, Outcome::Draw, Outcome::Win];
+
+    fn score(&self) -> u32 {
+        match self {
+            Outcome::Lose => 0,
+            Outcome::Draw => 3,
+            Outcome::Win => 6,
+        }
+    }
+}
+
+/// A cyclic game with an odd number of shapes. Listed in order, every shape beats the shapes an
+/// odd number of places before it (wrapping around) and loses to the others. A shape scores its
+/// 1-based position in the list.
+struct Game {
+    shapes: Vec<&'static str>,
+}
+
+impl Game {
+    fn new(shapes: Vec<&'static str>) -> Result<Self> {
+        if shapes.len().is_multiple_of(2) {
+            return Err(anyhow!("a fair game needs an odd number of shapes"));
+        }
+        Ok(Self { shapes })
+    }
+
+    fn rock_paper_scissors() -> Self {
+        Self::new(vec!["Rock", "Paper", "Scissors"]).unwrap()
+    }
+
+    #[cfg(test)]
+    fn rock_paper_scissors_lizard_spock() -> Self {
+        Self::new(vec!["Rock", "Paper", "Scissors", "Spock", "Lizard"]).unwrap()
+    }
+
+    fn outcome(&self, me: Shape, opponent: Shape) -> Outcome {
+        let n = self.shapes.len();
+        match (me.0 + n - opponent.0) % n {
+            0 => Outcome::Draw,
+            d if !d.is_multiple_of(2) => Outcome::Win,
+            _ => Outcome::Lose,
+        }
+    }
+
+    /// The shape to play against `opponent` for the given outcome. If several shapes qualify, the
+    /// one closest to `opponent` in the list is picked.
+    fn respond(&self, opponent: Shape, outcome: Outcome) -> Shape {
+        let n = self.shapes.len();
+        match outcome {
+            Outcome::Draw => opponent,
+            Outcome::Win => Shape((opponent.0 + 1) % n),
+            Outcome::Lose => Shape((opponent.0 + n - 1) % n),
+        }
+    }
+
+    fn round_score(&self, me: Shape, opponent: Shape) -> u32 {
+        me.0 as u32 + 1 + self.outcome(me, opponent).score()
+    }
+
+    fn score(&self, input: Input, guide: &Guide) -> Result<u32> {
+        input
+            .as_str()
+            .lines()
+            .enumerate()
+            .map(|(i, l)| {
+                let (opponent, me) = guide
+                    .parse_round(self, l)
+                    .with_context(|| format!("line {}: invalid round {:?}", i + 1, l))?;
+                Ok(self.round_score(me, opponent))
+            })
+            .sum()
+    }
+}
+
+/// What the second column of the strategy guide means.
+enum Response {
+    /// symbols of the shapes to play, in game order.
+    Shape(Vec<char>),
+    /// symbols for lose, draw and win.
+    Outcome([char; 3]),
+}
+
+/// Maps the symbols of the strategy guide to the game.
+struct Guide {
+    /// symbols of the opponent's shapes, in game order.
+    opponent: Vec<char>,
+    response: Response,
+}
+
+impl Guide {
+    fn shapes(opponent: &str, me: &str) -> Self {
+        Self {
+            opponent: opponent.chars().collect(),
+            response: Response::Shape(me.chars().collect()),
+        }
+    }
+
+    fn outcomes(opponent: &str, outcomes: &str) -> Result<Self> {
+        let (lose, draw, win) = outcomes.chars().collect_tuple().with_context(|| {
+            format!(
+                "expected symbols for lose, draw and win, got {:?}",
+                outcomes
+            )
+        })?;
+        Ok(Self {
+            opponent: opponent.chars().collect(),
+            response: Response::Outcome([lose, draw, win]),
+        })
+    }
+
+    fn parse_round(&self, game: &Game, l: &str) -> Result<(Shape, Shape)> {
+        let (a, b) = l
+            .split_whitespace()
+            .map(|s| s.chars().exactly_one().ok())
+            .collect_tuple()
+            .context("expected two symbols")?;
+        let (a, b) = a.zip(b).context("symbols must be single characters")?;
+
+        let opponent = lookup(&self.opponent, a, game)?;
+        let me = match &self.response {
+            Response::Shape(symbols) => lookup(symbols, b, game)?,
+            Response::Outcome(symbols) => {
+                let i = symbols
+                    .iter()
+                    .position(|&s| s == b)
+                    .with_context(|| format!("unknown outcome {:?}", b))?;
+                game.respond(opponent, Outcome::ALL[i])
+            }
+        };
+        Ok((opponent, me))
+    }
 }
 
-fn parse_move(l: &str) -> (&str, &str) {
-    l.split(' ').collect_tuple().unwrap()
+fn lookup(symbols: &[char], c: char, game: &Game) -> Result<Shape> {
+    symbols
+        .iter()
+        .position(|&s| s == c)
+        .filter(|&i| i < game.shapes.len())
+        .map(Shape)
+        .with_context(|| format!("unknown shape {:?}", c))
 }
 
 fn main() {
@@ -66,4 +186,24 @@ mod tests {
         let input = advent_of_code::read_file("examples", 2);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 12);
     }
+
+    #[test]
+    fn test_lizard_spock() {
+        let game = Game::rock_paper_scissors_lizard_spock();
+        let guide = Guide::shapes("RPSKL", "rpskl");
+        // spock vaporizes rock, lizard poisons spock, rock crushes lizard.
+        let input = "R k\nK l\nL r\nS s";
+        assert_eq!(
+            game.score(Input::new(input), &guide).unwrap(),
+            (4 + 6) + (5 + 6) + (1 + 6) + (3 + 3)
+        );
+        assert!(Game::new(vec!["Rock", "Paper"]).is_err());
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        let err = part_one(Input::new("A X\nA Q\n")).unwrap_err();
+        assert!(format!("{:#}", err).starts_with("line 2:"));
+        assert!(Guide::outcomes("ABC", "XY").is_err());
+    }
 }
@@ -1,57 +1,100 @@
 use advent_of_code::helpers::Input;
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use itertools::Itertools;
-use std::collections::HashSet;
 
 pub fn part_one(input: Input) -> Result<u32> {
-    Ok(input
-        .split_and_tform_lines(|l| {
+    input
+        .as_str()
+        .lines()
+        .enumerate()
+        .map(|(i, l)| {
             let (c0, c1) = l.split_at(l.len() / 2);
-            find_collision(c0, c1)
+            let common = ItemSet::from_items(c0)?.intersection(ItemSet::from_items(c1)?);
+            common
+                .single()
+                .with_context(|| format!("rucksack {}", i + 1))
         })
-        .sum())
+        .sum()
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
-    Ok(input
-        .as_str()
-        .lines()
-        .chunks(3)
-        .into_iter()
+    group_badges(input, 3)?
+        .iter()
+        .enumerate()
+        .map(|(i, badges)| badges.single().with_context(|| format!("group {}", i + 1)))
+        .sum()
+}
+
+/// Items common to all rucksacks of each group of `group_size` consecutive rucksacks.
+fn group_badges(input: Input, group_size: usize) -> Result<Vec<ItemSet>> {
+    let lines = input.as_str().lines().collect_vec();
+    if group_size == 0 || !lines.len().is_multiple_of(group_size) {
+        return Err(anyhow!(
+            "{} rucksacks can't be split into groups of {}",
+            lines.len(),
+            group_size
+        ));
+    }
+
+    lines
+        .chunks(group_size)
         .map(|g| {
-            *g.map(|l| {
-                l.as_bytes()
-                    .iter()
-                    .map(to_priority)
-                    .collect::<HashSet<u32>>()
-            })
-            .reduce(|a, b| a.intersection(&b).cloned().collect())
-            .unwrap()
-            .iter()
-            .next()
-            .unwrap()
+            g.iter()
+                .map(|l| ItemSet::from_items(l))
+                .fold_ok(ItemSet::ALL, ItemSet::intersection)
         })
-        .sum())
+        .collect()
 }
 
-fn find_collision(c0: &str, c1: &str) -> u32 {
-    let mut items = HashSet::new();
-    c0.as_bytes().iter().for_each(|c| {
-        items.insert(to_priority(c));
-    });
-    c1.as_bytes()
-        .iter()
-        .filter(|c| items.contains(&to_priority(c)))
-        .map(to_priority)
-        .next()
-        .unwrap()
+/// A set of item types, one bit per priority (`a-z` are 1-26, `A-Z` 27-52).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    const ALL: ItemSet = ItemSet((1 << 52) - 1);
+
+    fn from_items(items: &str) -> Result<Self> {
+        items.bytes().try_fold(ItemSet::default(), |set, c| {
+            Ok(set.union(ItemSet(1 << (to_priority(c)? - 1))))
+        })
+    }
+
+    fn intersection(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    fn union(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+
+    fn len(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    fn priorities(self) -> impl Iterator<Item = u32> {
+        (0..52)
+            .filter(move |i| self.0 & (1 << i) != 0)
+            .map(|i| i + 1)
+    }
+
+    /// The priority of the only item in the set.
+    fn single(&self) -> Result<u32> {
+        match self.len() {
+            1 => Ok(self.0.trailing_zeros() + 1),
+            0 => Err(anyhow!("no common item")),
+            _ => Err(anyhow!(
+                "ambiguous common items with priorities {}",
+                self.priorities().join(", ")
+            )),
+        }
+    }
 }
 
-fn to_priority(c: &u8) -> u32 {
+fn to_priority(c: u8) -> Result<u32> {
     match c {
-        (97..=122) => (c - 96).into(),
-        (65..=90) => (c - 38).into(),
-        _ => panic!("Bad char {c}"),
+        b'a'..=b'z' => Ok((c - b'a' + 1).into()),
+        b'A'..=b'Z' => Ok((c - b'A' + 27).into()),
+        _ => Err(anyhow!("bad item {:?}", c as char)),
     }
 }
 
@@ -76,4 +119,16 @@ mod tests {
         let input = advent_of_code::read_file("examples", 3);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 70);
     }
+
+    #[test]
+    fn test_group_badges() {
+        let input = advent_of_code::read_file("examples", 3);
+        // all six rucksacks share no item, some pairs share more than one.
+        let six = group_badges(Input::new(&input), 6).unwrap();
+        assert_eq!(six, vec![ItemSet::default()]);
+        let pairs = group_badges(Input::new(&input), 2).unwrap();
+        assert!(pairs.iter().any(|b| b.len() > 1));
+        assert!(group_badges(Input::new(&input), 4).is_err());
+        assert!(part_one(Input::new("ab1ab1")).is_err());
+    }
 }
@@ -1,86 +1,99 @@
-// use std::ops::Range;
-use advent_of_code::helpers::Input;
-use anyhow::Result;
+use std::ops::RangeInclusive;
+
+use advent_of_code::helpers::{interval::Interval, Input};
+use anyhow::{Context, Result};
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
 
 pub fn part_one(input: Input) -> Result<u32> {
-    // Some(
-    //     get_ranges(input)
-    //         .filter(|(a, b)| range_contains(a, b) || range_contains(b, a))
-    //         .count() as u32,
-    // )
-
-    Ok(input
-        .split_and_tform_lines(parse_line)
-        .filter(ranges_contains)
-        .count() as u32)
+    count_pairs(input, |a, b| {
+        a.contains_interval(b) || b.contains_interval(a)
+    })
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
-    // Some(
-    //     get_ranges(input)
-    //         .filter(|(a, b)| ranges_overlap(a, b))
-    //         .count() as u32,
-    // )
+    count_pairs(input, |a, b| a.overlaps(b))
+}
 
-    Ok(input
-        .split_and_tform_lines(parse_line)
-        .filter(ranges_overlap)
-        .count() as u32)
+pub fn part_one_range(input: Input) -> Result<u32> {
+    count_range_pairs(input, |a, b| range_contains(a, b) || range_contains(b, a))
 }
 
-// Iterator approach
-// fn get_ranges(input: Input) -> impl Iterator<Item = (Range<u32>, Range<u32>)> + '_ {
-//     input.split_and_tform_lines(|l| {
-//         let mut i = l.split(',').map(parse_range);
-//         (i.next().unwrap(), i.next().unwrap())
-//     })
-// }
-
-// fn parse_range(s: &str) -> Range<u32> {
-//     let mut i = s.split('-').map(|a| a.parse::<u32>().unwrap());
-//     Range {
-//         start: i.next().unwrap(),
-//         end: i.next().unwrap(),
-//     }
-// }
-
-// fn range_contains(a: &Range<u32>, b: &Range<u32>) -> bool {
-//     a.start <= b.start && a.end >= b.end
-// }
-
-// fn ranges_overlap(a: &Range<u32>, b: &Range<u32>) -> bool {
-//     b.start <= a.end && a.start <= b.end
-// }
+pub fn part_two_range(input: Input) -> Result<u32> {
+    count_range_pairs(input, range_overlaps)
+}
+
+fn count_pairs(input: Input, f: impl Fn(&Interval<u32>, &Interval<u32>) -> bool) -> Result<u32> {
+    input
+        .split_and_tform_lines(parse_line)
+        .filter_ok(|(a, b)| f(a, b))
+        .fold_ok(0, |count, _| count + 1)
+}
 
 // Regex approach, much cleaner
-fn parse_line(l: &str) -> (u32, u32, u32, u32) {
+fn parse_line(l: &str) -> Result<(Interval<u32>, Interval<u32>)> {
     lazy_static! {
-        static ref RE: Regex = Regex::new(r"(\d+)-(\d+),(\d+)-(\d+)").unwrap();
+        static ref RE: Regex = Regex::new(r"^(\d+)-(\d+),(\d+)-(\d+)$").unwrap();
     }
-    RE.captures(l)
-        .unwrap()
+    let caps = RE
+        .captures(l)
+        .with_context(|| format!("invalid assignment pair {:?}", l))?;
+    let (a, b, c, d) = caps
         .iter()
         .skip(1)
-        .map(|c| c.unwrap().as_str().parse::<u32>().unwrap())
-        .next_tuple()
-        .unwrap()
+        .map(|c| {
+            c.context("missing section")?
+                .as_str()
+                .parse::<u32>()
+                .map_err(Into::into)
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .collect_tuple()
+        .context("expected four sections")?;
+    let section =
+        |a, b| Interval::try_inclusive(a, b).with_context(|| format!("section {} is too large", b));
+    Ok((section(a, b)?, section(c, d)?))
+}
+
+// Iterator approach
+fn count_range_pairs(
+    input: Input,
+    f: impl Fn(&RangeInclusive<u32>, &RangeInclusive<u32>) -> bool,
+) -> Result<u32> {
+    input
+        .split_and_tform_lines(|l| {
+            let (a, b) = l
+                .split_once(',')
+                .with_context(|| format!("invalid assignment pair {:?}", l))?;
+            Ok::<_, anyhow::Error>((parse_range(a)?, parse_range(b)?))
+        })
+        .filter_ok(|(a, b)| f(a, b))
+        .fold_ok(0, |count, _| count + 1)
+}
+
+fn parse_range(s: &str) -> Result<RangeInclusive<u32>> {
+    let (start, end) = s
+        .split_once('-')
+        .with_context(|| format!("invalid range {:?}", s))?;
+    Ok(start.parse()?..=end.parse()?)
 }
 
-fn ranges_contains((a_start, a_end, b_start, b_end): &(u32, u32, u32, u32)) -> bool {
-    a_start <= b_start && a_end >= b_end || b_start <= a_start && b_end >= a_end
+fn range_contains(a: &RangeInclusive<u32>, b: &RangeInclusive<u32>) -> bool {
+    a.start() <= b.start() && a.end() >= b.end()
 }
 
-fn ranges_overlap((a_start, a_end, b_start, b_end): &(u32, u32, u32, u32)) -> bool {
-    b_start <= a_end && a_start <= b_end
+fn range_overlaps(a: &RangeInclusive<u32>, b: &RangeInclusive<u32>) -> bool {
+    b.start() <= a.end() && a.start() <= b.end()
 }
 
 fn main() {
-    let input = &advent_of_code::read_file("inputs", 4);
-    advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
-    advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
+    advent_of_code::solve_impls!(
+        4,
+        [interval: part_one, range: part_one_range],
+        [interval: part_two, range: part_two_range]
+    );
 }
 
 #[cfg(test)]
@@ -98,4 +111,18 @@ mod tests {
         let input = advent_of_code::read_file("examples", 4);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 4);
     }
+
+    #[test]
+    fn test_range_impl() {
+        let input = advent_of_code::read_file("examples", 4);
+        assert_eq!(part_one_range(Input::new(&input)).unwrap(), 2);
+        assert_eq!(part_two_range(Input::new(&input)).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_invalid_line() {
+        assert!(part_one(Input::new("2-4,6-8\n2-4;6-8\n")).is_err());
+        assert!(part_one_range(Input::new("2-4,6\n")).is_err());
+        assert!(part_one(Input::new("1-4294967295,2-3\n")).is_err());
+    }
 }
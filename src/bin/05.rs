@@ -1,50 +1,164 @@
 use advent_of_code::helpers::Input;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
 
 pub fn part_one(input: Input) -> Result<String> {
-    let (mut stacks, insts) = parse_input(input)?;
-    for i in insts {
-        for _ in 0..i.count {
-            let x = stacks[i.from - 1].pop().context("invalid pop")?;
-            stacks[i.to - 1].push(x);
-        }
+    let (stacks, insts) = parse_input(input)?;
+    let mut sim = CraneSim::new(CrateMover9000, stacks);
+    sim.run(&insts)?;
+    Ok(sim.stack_tops())
+}
+
+pub fn part_two(input: Input) -> Result<String> {
+    let (stacks, insts) = parse_input(input)?;
+    let mut sim = CraneSim::new(CrateMover9001, stacks);
+    sim.run(&insts)?;
+    Ok(sim.stack_tops())
+}
+
+/// How a crane model puts down the crates it lifted off a stack.
+trait Crane {
+    /// `lifted` holds the moved crates bottom to top as they were on the source stack and is
+    /// rearranged into the order they end up on the target stack.
+    fn arrange(&self, lifted: &mut [char]);
+}
+
+/// Moves one crate at a time, reversing their order.
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn arrange(&self, lifted: &mut [char]) {
+        lifted.reverse();
     }
+}
 
-    Ok(stack_tops(&stacks))
+/// Moves all crates at once, keeping their order.
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn arrange(&self, _lifted: &mut [char]) {}
 }
 
-pub fn part_two(input: Input) -> Result<String> {
-    let (mut stacks, insts) = parse_input(input)?;
-    for i in insts {
-        let mut cs: Vec<char> = vec![];
-        for _ in 0..i.count {
-            cs.push(stacks[i.from - 1].pop().context("invalid pop")?);
+/// Applies instructions to the stacks and records every move so that the stacks can be
+/// reconstructed after any step.
+struct CraneSim<C> {
+    crane: C,
+    initial: Vec<Vec<char>>,
+    stacks: Vec<Vec<char>>,
+    moves: Vec<Instruction>,
+}
+
+impl<C: Crane> CraneSim<C> {
+    fn new(crane: C, stacks: Vec<Vec<char>>) -> Self {
+        Self {
+            crane,
+            initial: stacks.clone(),
+            stacks,
+            moves: vec![],
+        }
+    }
+
+    fn run(&mut self, insts: &[Instruction]) -> Result<()> {
+        for (n, i) in insts.iter().enumerate() {
+            self.step(i)
+                .with_context(|| format!("instruction {}: {}", n + 1, i))?;
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, i: &Instruction) -> Result<()> {
+        apply(&self.crane, &mut self.stacks, i)?;
+        self.moves.push(i.clone());
+        Ok(())
+    }
+
+    /// The stacks after the first `step` recorded moves.
+    fn replay(&self, step: usize) -> Result<Vec<Vec<char>>> {
+        if step > self.moves.len() {
+            return Err(anyhow!(
+                "only {} moves were recorded, can't replay {}",
+                self.moves.len(),
+                step
+            ));
+        }
+
+        let mut stacks = self.initial.clone();
+        for i in &self.moves[..step] {
+            apply(&self.crane, &mut stacks, i)?;
         }
-        stacks[i.to - 1].extend(cs.iter().rev());
+        Ok(stacks)
     }
 
-    Ok(stack_tops(&stacks))
+    /// The crate on top of every stack, with a space for an empty stack.
+    fn stack_tops(&self) -> String {
+        self.stacks
+            .iter()
+            .map(|s| s.last().copied().unwrap_or(' '))
+            .collect()
+    }
+}
+
+fn apply(crane: &impl Crane, stacks: &mut [Vec<char>], i: &Instruction) -> Result<()> {
+    let stack = |n: usize| {
+        n.checked_sub(1)
+            .filter(|&idx| idx < stacks.len())
+            .with_context(|| format!("stack {} does not exist", n))
+    };
+    let (from, to) = (stack(i.from)?, stack(i.to)?);
+
+    let available = stacks[from].len();
+    if i.count > available {
+        return Err(anyhow!(
+            "can't move {} crates from stack {} holding {}",
+            i.count,
+            i.from,
+            available
+        ));
+    }
+
+    let mut lifted = stacks[from].split_off(available - i.count);
+    crane.arrange(&mut lifted);
+    stacks[to].extend(lifted);
+    Ok(())
+}
+
+/// Draws the stacks in the puzzle's crate diagram format, the inverse of `parse_initial_state`.
+fn render(stacks: &[Vec<char>]) -> String {
+    let height = stacks.iter().map(Vec::len).max().unwrap_or(0);
+    let mut rows = (0..height)
+        .rev()
+        .map(|h| {
+            stacks
+                .iter()
+                .map(|s| match s.get(h) {
+                    Some(c) => format!("[{}]", c),
+                    None => "   ".to_string(),
+                })
+                .join(" ")
+        })
+        .collect_vec();
+    rows.push((1..=stacks.len()).map(|n| format!(" {} ", n)).join(" "));
+    rows.join("\n")
 }
 
 fn parse_input(input: Input) -> Result<(Vec<Vec<char>>, Vec<Instruction>)> {
-    let (initial, instructions) = input.split("\n\n");
+    let (initial, instructions) = input
+        .as_str()
+        .split_once("\n\n")
+        .context("no empty line between stacks and instructions")?;
     let stacks = parse_initial_state(initial)?;
     let insts: Vec<Instruction> = instructions
         .lines()
-        .map(|l| Instruction::from_string(l).unwrap())
-        .collect();
+        .enumerate()
+        .map(|(n, l)| {
+            Instruction::from_string(l).with_context(|| format!("instruction {}: {:?}", n + 1, l))
+        })
+        .collect::<Result<_>>()?;
     Ok((stacks, insts))
 }
 
-fn stack_tops(stacks: &[Vec<char>]) -> String {
-    stacks
-        .iter()
-        .map(|s| s.last().unwrap())
-        .fold("".to_string(), |ret, c| format!("{ret}{c}"))
-}
-
 fn parse_initial_state(s: &str) -> Result<Vec<Vec<char>>> {
     let num = s
         .lines()
@@ -56,23 +170,33 @@ fn parse_initial_state(s: &str) -> Result<Vec<Vec<char>>> {
         .parse::<usize>()?;
     let mut stacks = vec![Vec::<char>::new(); num];
 
-    s.lines().rev().skip(1).for_each(|l| {
+    for l in s.lines().rev().skip(1) {
         for (i, c) in l.chars().enumerate() {
             if c.is_uppercase() {
-                stacks[(i - 1) / 4].push(c)
+                stacks
+                    .get_mut(i / 4)
+                    .with_context(|| format!("crate {} outside of the {} stacks", c, num))?
+                    .push(c)
             }
         }
-    });
+    }
 
     Ok(stacks)
 }
 
+#[derive(Debug, Clone)]
 struct Instruction {
     count: usize,
     from: usize,
     to: usize,
 }
 
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.from, self.to)
+    }
+}
+
 impl Instruction {
     fn from_string(s: &str) -> Result<Self> {
         lazy_static! {
@@ -100,8 +224,45 @@ impl Instruction {
     }
 }
 
+/// Runs all instructions and renders the stacks as they were after `step` of them.
+fn render_step(crane: impl Crane, input: Input, step: usize) -> Result<String> {
+    let (stacks, insts) = parse_input(input)?;
+    let mut sim = CraneSim::new(crane, stacks);
+    sim.run(&insts)?;
+    Ok(render(&sim.replay(step)?))
+}
+
+struct Args {
+    step: Option<usize>,
+    crane: u32,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        step: args.opt_value_from_str("--step")?,
+        crane: args.opt_value_from_str("--crane")?.unwrap_or(9001),
+    })
+}
+
 fn main() {
     let input = &advent_of_code::read_file("inputs", 5);
+
+    // `--step <n> [--crane 9000|9001]` draws the stacks after the first n instructions.
+    let args = parse_args().expect("invalid arguments");
+    if let Some(step) = args.step {
+        let rendered = match args.crane {
+            9000 => render_step(CrateMover9000, Input::new(input), step),
+            9001 => render_step(CrateMover9001, Input::new(input), step),
+            c => Err(anyhow!("unknown crane model CrateMover {}", c)),
+        };
+        match rendered {
+            Ok(r) => println!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -121,4 +282,49 @@ mod tests {
         let input = advent_of_code::read_file("examples", 5);
         assert_eq!(part_two(Input::new(&input)).unwrap(), "MCD".to_string());
     }
+
+    #[test]
+    fn test_render_replay() {
+        let input = advent_of_code::read_file("examples", 5);
+        let (stacks, insts) = parse_input(Input::new(&input)).unwrap();
+        let initial = input.split("\n\n").next().unwrap();
+        assert_eq!(render(&stacks), initial);
+
+        let mut sim = CraneSim::new(CrateMover9000, stacks);
+        sim.run(&insts).unwrap();
+        assert_eq!(
+            render(&sim.replay(1).unwrap()),
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 "
+        );
+        assert_eq!(sim.replay(insts.len()).unwrap(), sim.stacks);
+    }
+
+    #[test]
+    fn test_invalid_instruction() {
+        let input = advent_of_code::read_file("examples", 5);
+        let (stacks, _) = parse_input(Input::new(&input)).unwrap();
+        let mut sim = CraneSim::new(CrateMover9000, stacks);
+        let insts = [
+            Instruction::from_string("move 1 from 2 to 1").unwrap(),
+            Instruction::from_string("move 1 from 0 to 1").unwrap(),
+        ];
+        let err = sim.run(&insts).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "instruction 2: move 1 from 0 to 1: stack 0 does not exist"
+        );
+
+        let insts = [Instruction::from_string("move 9 from 3 to 1").unwrap()];
+        assert!(sim.run(&insts).is_err());
+    }
+
+    #[test]
+    fn test_empty_stack() {
+        let input = advent_of_code::read_file("examples", 5);
+        let (stacks, _) = parse_input(Input::new(&input)).unwrap();
+        let mut sim = CraneSim::new(CrateMover9000, stacks);
+        let insts = [Instruction::from_string("move 1 from 3 to 1").unwrap()];
+        sim.run(&insts).unwrap();
+        assert_eq!(sim.stack_tops(), "PD ");
+    }
 }
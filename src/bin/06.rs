@@ -1,35 +1,173 @@
-use std::collections::{hash_map::Entry, HashMap};
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    fs::File,
+    io::{self, BufRead, BufReader},
+};
 
 use advent_of_code::helpers::Input;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 
 pub fn part_one(input: Input) -> Result<u32> {
-    distinct_window_array(input, 4)
+    first_marker(stream(input), 4)
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
-    distinct_window_array(input, 14)
+    first_marker(stream(input), 14)
 }
 
-fn byte_to_idx(i: u8) -> usize {
-    (i - b'a') as usize
+pub fn part_one_array(input: Input) -> Result<u32> {
+    distinct_window_array(stream(input), 4)
+}
+
+pub fn part_two_array(input: Input) -> Result<u32> {
+    distinct_window_array(stream(input), 14)
+}
+
+pub fn part_one_map(input: Input) -> Result<u32> {
+    distinct_window_map(stream(input), 4)
+}
+
+pub fn part_two_map(input: Input) -> Result<u32> {
+    distinct_window_map(stream(input), 14)
+}
+
+/// The datastream without the line break ending the input file.
+fn stream<'a>(input: Input<'a>) -> &'a [u8] {
+    input.as_str().trim_end_matches(['\r', '\n']).as_bytes()
+}
+
+fn first_marker(reader: impl BufRead, window_len: usize) -> Result<u32> {
+    let marker = Markers::new(reader, &[window_len])
+        .next()
+        .context("Not found")??;
+    Ok(marker.offset as u32)
+}
+
+/// A run of distinct bytes ending at `offset`, the number of bytes read so far.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Marker {
+    pub window_len: usize,
+    pub offset: usize,
+}
+
+/// Byte counts of the last `len` bytes of the stream.
+struct Window {
+    len: usize,
+    counts: [u32; 256],
+    distinct: usize,
+}
+
+/// Tracks any number of window lengths over the same stream, keeping only the last
+/// `max(window_len)` bytes around.
+struct MarkerDetector {
+    windows: Vec<Window>,
+    history: Vec<u8>,
+    pos: usize,
+}
+
+impl MarkerDetector {
+    fn new(window_lens: &[usize]) -> Self {
+        let windows = window_lens
+            .iter()
+            .map(|&len| Window {
+                len,
+                counts: [0; 256],
+                distinct: 0,
+            })
+            .collect();
+        let max_len = window_lens.iter().copied().max().unwrap_or(0);
+        Self {
+            windows,
+            history: vec![0; max_len],
+            pos: 0,
+        }
+    }
+
+    /// Feeds the next byte and returns the markers ending with it.
+    fn push(&mut self, b: u8) -> impl Iterator<Item = Marker> + '_ {
+        let (history, pos) = (&self.history, self.pos);
+        for w in &mut self.windows {
+            if w.len == 0 {
+                continue;
+            }
+            w.counts[b as usize] += 1;
+            if w.counts[b as usize] == 1 {
+                w.distinct += 1;
+            }
+            if pos >= w.len {
+                let rm = history[(pos - w.len) % history.len()] as usize;
+                w.counts[rm] -= 1;
+                if w.counts[rm] == 0 {
+                    w.distinct -= 1;
+                }
+            }
+        }
+
+        if !self.history.is_empty() {
+            let len = self.history.len();
+            self.history[pos % len] = b;
+        }
+        self.pos += 1;
+
+        let offset = self.pos;
+        self.windows
+            .iter()
+            .filter(|w| w.len > 0 && w.distinct == w.len)
+            .map(move |w| Marker {
+                window_len: w.len,
+                offset,
+            })
+    }
+}
+
+/// Every marker of the given window lengths in a stream, found in a single pass. Markers ending
+/// at the same offset are listed in the order of `window_lens`.
+pub struct Markers<R> {
+    bytes: io::Bytes<R>,
+    detector: MarkerDetector,
+    pending: VecDeque<Marker>,
+}
+
+impl<R: BufRead> Markers<R> {
+    pub fn new(reader: R, window_lens: &[usize]) -> Self {
+        Self {
+            bytes: reader.bytes(),
+            detector: MarkerDetector::new(window_lens),
+            pending: VecDeque::new(),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Markers<R> {
+    type Item = io::Result<Marker>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(m) = self.pending.pop_front() {
+                return Some(Ok(m));
+            }
+            match self.bytes.next()? {
+                Ok(b) => self.pending.extend(self.detector.push(b)),
+                Err(e) => return Some(Err(e)),
+            }
+        }
+    }
 }
 
 // 2nd attempt after seeing answers, way faster
-fn distinct_window_array(input: Input, window_len: usize) -> Result<u32> {
-    let bytes = input.as_bytes();
-    let mut window_counts = [0_u8; 26];
+fn distinct_window_array(bytes: &[u8], window_len: usize) -> Result<u32> {
+    let mut window_counts = [0_u32; 256];
     let mut unique_count = 0;
 
     for i in 0..bytes.len() {
-        let add_idx = byte_to_idx(bytes[i]);
+        let add_idx = bytes[i] as usize;
         window_counts[add_idx] += 1;
         if window_counts[add_idx] == 1 {
             unique_count += 1;
         }
 
         if i >= window_len {
-            let rm_idx = byte_to_idx(bytes[i - window_len]);
+            let rm_idx = bytes[i - window_len] as usize;
             window_counts[rm_idx] -= 1;
             if window_counts[rm_idx] == 0 {
                 unique_count -= 1;
@@ -44,8 +182,7 @@ fn distinct_window_array(input: Input, window_len: usize) -> Result<u32> {
     Err(anyhow!("Not found"))
 }
 
-fn _distinct_window_map(input: Input, window_len: usize) -> Result<u32> {
-    let bytes = input.as_bytes();
+fn distinct_window_map(bytes: &[u8], window_len: usize) -> Result<u32> {
     let mut window_vals = HashMap::new();
 
     for i in 0..bytes.len() {
@@ -74,14 +211,33 @@ fn _distinct_window_map(input: Input, window_len: usize) -> Result<u32> {
 }
 
 fn main() {
-    let input = &advent_of_code::read_file("inputs", 6);
-    advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
-    advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
+    // `--markers <file>` streams a file and lists every start-of-packet and start-of-message
+    // marker in one pass.
+    let mut args = pico_args::Arguments::from_env();
+    let path: Option<String> = args
+        .opt_value_from_str("--markers")
+        .expect("invalid arguments");
+    if let Some(path) = path {
+        let file = File::open(&path).expect("could not open input file");
+        for m in Markers::new(BufReader::new(file), &[4, 14]) {
+            let m = m.expect("could not read input file");
+            println!("{:>2} distinct bytes ending at {}", m.window_len, m.offset);
+        }
+        return;
+    }
+
+    advent_of_code::solve_impls!(
+        6,
+        [stream: part_one, array: part_one_array, map: part_one_map],
+        [stream: part_two, array: part_two_array, map: part_two_map]
+    );
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
+    use proptest::prelude::*;
 
     #[test]
     fn test_part_one() {
@@ -94,4 +250,49 @@ mod tests {
         let input = advent_of_code::read_file("examples", 6);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 19);
     }
+
+    #[test]
+    fn test_markers() {
+        let input = advent_of_code::read_file("examples", 6);
+        let markers = Markers::new(stream(Input::new(&input)), &[4, 14])
+            .map(Result::unwrap)
+            .take(4)
+            .collect::<Vec<_>>();
+        let m = |window_len, offset| Marker { window_len, offset };
+        assert_eq!(markers, vec![m(4, 7), m(4, 8), m(4, 9), m(4, 10)]);
+
+        let first_message = Markers::new(stream(Input::new(&input)), &[4, 14])
+            .map(Result::unwrap)
+            .find(|m| m.window_len == 14);
+        assert_eq!(first_message, Some(m(14, 19)));
+        assert_eq!(first_marker(&[0xff, 0, b'\n', 0xff][..], 3).unwrap(), 3);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_window_impls_agree(stream in "[a-z]{0,200}", window_len in 1_usize..=14) {
+            let bytes = stream.as_bytes();
+            let array = distinct_window_array(bytes, window_len).ok();
+            prop_assert_eq!(array, distinct_window_map(bytes, window_len).ok());
+            prop_assert_eq!(array, first_marker(bytes, window_len).ok());
+        }
+
+        #[test]
+        fn prop_markers_match_naive(
+            bytes in proptest::collection::vec(any::<u8>(), 0..200),
+            lens in proptest::collection::vec(1_usize..=14, 1..4),
+        ) {
+            let found = Markers::new(&bytes[..], &lens)
+                .map(Result::unwrap)
+                .collect::<Vec<_>>();
+            let naive = (1..=bytes.len())
+                .flat_map(|end| lens.iter().map(move |&len| (end, len)))
+                .filter(|&(end, len)| {
+                    end >= len && bytes[end - len..end].iter().all_unique()
+                })
+                .map(|(offset, window_len)| Marker { window_len, offset })
+                .collect::<Vec<_>>();
+            prop_assert_eq!(found, naive);
+        }
+    }
 }
@@ -1,198 +1,332 @@
-use std::collections::HashMap;
-use std::fmt::{self, Formatter};
-use std::rc::{Rc, Weak};
-use std::{cell::RefCell, fmt::Debug};
+use std::collections::BTreeMap;
+use std::fmt::Write;
 
 use advent_of_code::helpers::Input;
 use anyhow::{anyhow, Context, Result};
 
+const DISK_SIZE: usize = 70000000;
+const REQUIRED_SPACE: usize = 30000000;
+
 pub fn part_one(input: Input) -> Result<u32> {
     let fs = populate_fs(input)?;
-    let sum: usize = fs_dir_sizes(&fs)?
-        .iter()
-        .skip(1)
-        .filter(|s| **s <= 100000)
-        .sum();
+    let sum: usize = fs.dirs().map(|d| fs.size(d)).filter(|s| *s <= 100000).sum();
     Ok(sum as u32)
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
     let fs = populate_fs(input)?;
-    let mut sizes = fs_dir_sizes(&fs)?;
-    sizes.sort();
-    let total_size = sizes.last().context("no sizes")?;
-    let needed = total_size - (70000000 - 30000000);
-    sizes
-        .iter()
-        .find(|s| **s >= needed)
-        .map(|i| *i as u32)
-        .context("no dir found")
+    let dir = fs.smallest_dir_freeing(DISK_SIZE, REQUIRED_SPACE)?;
+    Ok(fs.size(dir) as u32)
 }
 
 fn populate_fs(input: Input) -> Result<Filesystem> {
     let mut fs = Filesystem::new();
+    let mut listing = None;
 
-    for l in input.as_str().lines() {
+    for (n, l) in input.as_str().lines().enumerate() {
         let parts: Vec<&str> = l.split_whitespace().collect();
-        match parts[0] {
-            "$" => match parts[1] {
-                "cd" => fs.cd(parts[2])?,
-                "ls" => {}
-                _ => return Err(anyhow!("command {:?} not recognized", parts[1])),
-            },
-            "dir" => fs.mkdir(parts[1])?,
-            _ => fs.touch(parts[1], parts[0].parse()?)?,
-        }
+        let res = match parts[..] {
+            ["$", "cd", dir] => {
+                listing = None;
+                fs.cd(dir)
+            }
+            ["$", "ls"] => {
+                listing = Some(fs.cwd);
+                fs.ls()
+            }
+            ["$", ..] => Err(anyhow!("unknown command {:?}", &l[1..].trim())),
+            ["dir", name] => listing
+                .context("directory entry outside of `ls` output")
+                .and_then(|dir| fs.mkdir(dir, name).map(|_| ())),
+            [size, name] => listing
+                .context("file entry outside of `ls` output")
+                .and_then(|dir| {
+                    let size = size
+                        .parse()
+                        .with_context(|| format!("invalid file size {:?}", size))?;
+                    fs.touch(dir, name, size).map(|_| ())
+                }),
+            _ => Err(anyhow!("unrecognized line")),
+        };
+        res.with_context(|| format!("line {}: {:?}", n + 1, l))?;
     }
 
     Ok(fs)
 }
 
-fn fs_dir_sizes(fs: &Filesystem) -> Result<Vec<usize>> {
-    let mut ret = vec![];
-    let mut dirs_todo = vec![fs.root.clone()];
-    while !dirs_todo.is_empty() {
-        let cur = dirs_todo.pop().context("bad pop")?;
-        let curb = cur.borrow();
-        let size = curb.get_size()?;
-        ret.push(size);
-
-        if let Some(c) = &curb.children {
-            dirs_todo.extend(
-                c.iter()
-                    .map(|(_, v)| v.clone())
-                    .filter(|n| n.borrow().is_dir()),
-            );
-        } else {
-            return Err(anyhow!("directory without children"));
-        };
-    }
-    Ok(ret)
+/// Index of a node in the [`Filesystem`] arena.
+type NodeId = usize;
+
+enum NodeKind {
+    Dir {
+        children: BTreeMap<String, NodeId>,
+        listed: bool,
+    },
+    File,
+}
+
+struct Node {
+    name: String,
+    parent: Option<NodeId>,
+    /// the size of a file, or the total size of everything below a directory.
+    size: usize,
+    kind: NodeKind,
 }
 
+/// An arena of nodes rooted at `/`. Directory sizes are kept up to date as files are added,
+/// so size queries don't have to walk the tree.
 struct Filesystem {
-    root: Rc<RefCell<Node>>,
-    cur: Rc<RefCell<Node>>,
+    nodes: Vec<Node>,
+    cwd: NodeId,
 }
 
 impl Filesystem {
+    const ROOT: NodeId = 0;
+
     fn new() -> Self {
-        let _root = Rc::new(RefCell::new(Node::new_dir_node(None, "root")));
         Self {
-            root: _root.clone(),
-            cur: _root,
+            nodes: vec![Node {
+                name: "/".to_string(),
+                parent: None,
+                size: 0,
+                kind: NodeKind::Dir {
+                    children: BTreeMap::new(),
+                    listed: false,
+                },
+            }],
+            cwd: Self::ROOT,
         }
     }
 
-    fn mkdir(&mut self, name: &str) -> Result<()> {
-        self.cur
-            .borrow_mut()
-            .add_child(Rc::new(RefCell::new(Node::new_dir_node(
-                Some(&self.cur),
-                name,
-            ))))
-    }
-
-    fn touch(&mut self, name: &str, size: usize) -> Result<()> {
-        self.cur
-            .borrow_mut()
-            .add_child(Rc::new(RefCell::new(Node::new_file_node(
-                &self.cur, name, size,
-            ))))
-    }
-
-    fn cd(&mut self, cmd: &str) -> Result<()> {
-        self.cur = match cmd {
-            "/" => self.root.clone(),
-            ".." => self
-                .cur
-                .borrow()
-                .get_parent()?
-                .upgrade()
-                .context("parent no longer exists")?,
-            _ => self.cur.borrow().find_child(cmd)?,
+    fn cd(&mut self, dir: &str) -> Result<()> {
+        self.cwd = match dir {
+            "/" => Self::ROOT,
+            ".." => self.nodes[self.cwd]
+                .parent
+                .context("can't leave the root directory")?,
+            _ => self.child_dir(self.cwd, dir)?,
         };
-
         Ok(())
     }
-}
 
-struct Node {
-    name: String,
-    size: Option<usize>,
-    parent: Option<Weak<RefCell<Node>>>,
-    children: Option<HashMap<String, Rc<RefCell<Node>>>>,
-}
+    /// Marks the current directory as listed. Every directory may only be listed once, since a
+    /// second listing would add its files again.
+    fn ls(&mut self) -> Result<()> {
+        let path = self.path(self.cwd);
+        match &mut self.nodes[self.cwd].kind {
+            NodeKind::Dir { listed: true, .. } => Err(anyhow!("{} was already listed", path)),
+            NodeKind::Dir { listed, .. } => {
+                *listed = true;
+                Ok(())
+            }
+            NodeKind::File => Err(anyhow!("{} is not a directory", path)),
+        }
+    }
 
-impl Node {
-    fn new_dir_node(parent: Option<&Rc<RefCell<Node>>>, name: &str) -> Self {
-        Self {
-            name: name.to_string(),
-            size: None,
-            parent: parent.map(Rc::downgrade),
-            children: Some(HashMap::new()),
+    fn mkdir(&mut self, parent: NodeId, name: &str) -> Result<NodeId> {
+        self.insert(
+            parent,
+            name,
+            0,
+            NodeKind::Dir {
+                children: BTreeMap::new(),
+                listed: false,
+            },
+        )
+    }
+
+    fn touch(&mut self, parent: NodeId, name: &str, size: usize) -> Result<NodeId> {
+        let id = self.insert(parent, name, size, NodeKind::File)?;
+        let mut dir = Some(parent);
+        while let Some(d) = dir {
+            self.nodes[d].size += size;
+            dir = self.nodes[d].parent;
         }
+        Ok(id)
     }
 
-    fn new_file_node(parent: &Rc<RefCell<Node>>, name: &str, size: usize) -> Self {
-        Self {
+    fn insert(
+        &mut self,
+        parent: NodeId,
+        name: &str,
+        size: usize,
+        kind: NodeKind,
+    ) -> Result<NodeId> {
+        let id = self.nodes.len();
+        let path = self.path(parent);
+        let NodeKind::Dir { children, .. } = &mut self.nodes[parent].kind else {
+            return Err(anyhow!("{} is not a directory", path));
+        };
+        if children.contains_key(name) {
+            return Err(anyhow!("{:?} already exists in {}", name, path));
+        }
+        children.insert(name.to_string(), id);
+        self.nodes.push(Node {
             name: name.to_string(),
-            size: Some(size),
-            parent: Some(Rc::downgrade(parent)),
-            children: None,
+            parent: Some(parent),
+            size,
+            kind,
+        });
+        Ok(id)
+    }
+
+    fn children(&self, dir: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        let children = match &self.nodes[dir].kind {
+            NodeKind::Dir { children, .. } => Some(children.values().copied()),
+            NodeKind::File => None,
+        };
+        children.into_iter().flatten()
+    }
+
+    fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        match &self.nodes[dir].kind {
+            NodeKind::Dir { children, .. } => children.get(name).copied(),
+            NodeKind::File => None,
         }
     }
 
-    fn is_dir(&self) -> bool {
-        self.children.is_some()
+    fn child_dir(&self, dir: NodeId, name: &str) -> Result<NodeId> {
+        self.child(dir, name)
+            .filter(|&c| self.is_dir(c))
+            .with_context(|| format!("no directory {:?} in {}", name, self.path(dir)))
     }
 
-    fn add_child(&mut self, node: Rc<RefCell<Node>>) -> Result<()> {
-        let children = self
-            .children
-            .as_mut()
-            .context("not a directory, cannot add child")?;
-        let name = node.borrow().name.clone();
-        children.insert(name, node);
-        Ok(())
+    fn is_dir(&self, id: NodeId) -> bool {
+        matches!(self.nodes[id].kind, NodeKind::Dir { .. })
     }
 
-    fn get_parent(&self) -> Result<Weak<RefCell<Node>>> {
-        self.parent.clone().context("No parent, root node")
+    fn size(&self, id: NodeId) -> usize {
+        self.nodes[id].size
     }
 
-    fn find_child(&self, name: &str) -> Result<Rc<RefCell<Node>>> {
-        if let Some(children) = &self.children {
-            match children.get(name) {
-                Some(node) => Ok(node.clone()),
-                None => Err(anyhow!("Child not found")),
-            }
-        } else {
-            Err(anyhow!("File has no children"))
+    /// Every directory, including the root.
+    fn dirs(&self) -> impl Iterator<Item = NodeId> + '_ {
+        (0..self.nodes.len()).filter(|&id| self.is_dir(id))
+    }
+
+    /// Looks up an absolute path such as `/a/e`.
+    fn lookup(&self, path: &str) -> Result<NodeId> {
+        let rest = path
+            .strip_prefix('/')
+            .with_context(|| format!("{:?} is not an absolute path", path))?;
+        rest.split('/')
+            .filter(|p| !p.is_empty())
+            .try_fold(Self::ROOT, |id, name| {
+                self.child(id, name)
+                    .with_context(|| format!("{} not found", path))
+            })
+    }
+
+    fn path(&self, id: NodeId) -> String {
+        let mut names = vec![];
+        let mut node = Some(id);
+        while let Some(n) = node.filter(|&n| n != Self::ROOT) {
+            names.push(self.nodes[n].name.as_str());
+            node = self.nodes[n].parent;
         }
+        names.reverse();
+        format!("/{}", names.join("/"))
     }
 
-    fn get_size(&self) -> Result<usize> {
-        if let Some(children) = &self.children {
-            let mut sum = 0;
-            for (_, n) in children.iter() {
-                sum += n.borrow().get_size()?;
-            }
-            Ok(sum)
-        } else {
-            self.size.context("file without size")
+    /// The smallest directory whose deletion leaves at least `required` bytes free on a disk of
+    /// `disk` bytes.
+    fn smallest_dir_freeing(&self, disk: usize, required: usize) -> Result<NodeId> {
+        let used = self.size(Self::ROOT);
+        let free = disk
+            .checked_sub(used)
+            .with_context(|| format!("{} bytes used on a disk of {}", used, disk))?;
+        let needed = required.saturating_sub(free);
+        self.dirs()
+            .filter(|&d| self.size(d) >= needed)
+            .min_by_key(|&d| self.size(d))
+            .with_context(|| format!("no directory frees {} bytes", needed))
+    }
+
+    /// Renders the tree below `id` like the puzzle description does.
+    fn tree(&self, id: NodeId) -> String {
+        let mut out = String::new();
+        self.write_tree(&mut out, id, 0);
+        out
+    }
+
+    fn write_tree(&self, out: &mut String, id: NodeId, depth: usize) {
+        let node = &self.nodes[id];
+        let kind = if self.is_dir(id) { "dir" } else { "file" };
+        let indent = "  ".repeat(depth);
+        writeln!(
+            out,
+            "{}- {} ({}, size={})",
+            indent, node.name, kind, node.size
+        )
+        .unwrap();
+        for c in self.children(id) {
+            self.write_tree(out, c, depth + 1);
+        }
+    }
+
+    /// Lists the size of every directory below `id` like `du -h`, subdirectories before their
+    /// parents.
+    fn du(&self, id: NodeId) -> String {
+        let mut out = String::new();
+        for d in self.children(id).filter(|&c| self.is_dir(c)) {
+            out.push_str(&self.du(d));
         }
+        writeln!(out, "{}\t{}", human_size(self.size(id)), self.path(id)).unwrap();
+        out
     }
 }
 
-impl Debug for Node {
-    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Node")
-            .field("name", &self.name)
-            .field("size", &self.size)
-            .field("children", &self.children)
-            .finish()
+/// Formats a size with binary unit suffixes the way `du -h` does.
+fn human_size(size: usize) -> String {
+    let mut value = size as f64;
+    for unit in ["", "K", "M", "G", "T"] {
+        if value < 1024.0 || unit == "T" {
+            return if unit.is_empty() {
+                size.to_string()
+            } else if value < 10.0 {
+                format!("{:.1}{}", (value * 10.0).ceil() / 10.0, unit)
+            } else {
+                format!("{}{}", value.ceil(), unit)
+            };
+        }
+        value /= 1024.0;
+    }
+    unreachable!()
+}
+
+struct Args {
+    tree: Option<String>,
+    du: Option<String>,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        tree: args.opt_value_from_str("--tree")?,
+        du: args.opt_value_from_str("--du")?,
+    })
+}
+
+fn main() {
+    let input = &advent_of_code::read_file("inputs", 7);
+
+    // `--tree <path>` and `--du <path>` print the reconstructed filesystem below a directory.
+    let args = parse_args().expect("invalid arguments");
+    if args.tree.is_some() || args.du.is_some() {
+        let report = populate_fs(Input::new(input)).and_then(|fs| match (&args.tree, &args.du) {
+            (Some(path), _) => Ok(fs.tree(fs.lookup(path)?)),
+            (_, Some(path)) => Ok(fs.du(fs.lookup(path)?)),
+            _ => unreachable!(),
+        });
+        match report {
+            Ok(r) => print!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
     }
+
+    advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
+    advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
 
 #[cfg(test)]
@@ -210,10 +344,44 @@ mod tests {
         let input = advent_of_code::read_file("examples", 7);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 24933642);
     }
-}
 
-fn main() {
-    let input = &advent_of_code::read_file("inputs", 7);
-    advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
-    advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
+    #[test]
+    fn test_queries() {
+        let input = advent_of_code::read_file("examples", 7);
+        let fs = populate_fs(Input::new(&input)).unwrap();
+        let e = fs.lookup("/a/e").unwrap();
+        assert_eq!(fs.size(e), 584);
+        assert_eq!(fs.path(e), "/a/e");
+        assert!(fs.lookup("/a/x").is_err());
+
+        let d = fs.smallest_dir_freeing(48400000, 100000).unwrap();
+        assert_eq!(fs.path(d), "/a");
+
+        assert_eq!(
+            fs.tree(fs.lookup("/a").unwrap()),
+            "- a (dir, size=94853)\n  - e (dir, size=584)\n    - i (file, size=584)\n  - f (file, size=29116)\n  - g (file, size=2557)\n  - h.lst (file, size=62596)\n"
+        );
+        assert_eq!(
+            fs.du(Filesystem::ROOT),
+            "584\t/a/e\n93K\t/a\n24M\t/d\n47M\t/\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_session() {
+        let err = |s: &str| format!("{:#}", populate_fs(Input::new(s)).err().unwrap());
+        assert_eq!(
+            err("$ cd /\n$ ls\ndir a\n$ cd b\n"),
+            "line 4: \"$ cd b\": no directory \"b\" in /"
+        );
+        assert_eq!(
+            err("$ cd /\n$ ls\n$ ls\n"),
+            "line 3: \"$ ls\": / was already listed"
+        );
+        assert_eq!(
+            err("$ cd /\n$ rm -rf a\n"),
+            "line 2: \"$ rm -rf a\": unknown command \"rm -rf a\""
+        );
+        assert!(populate_fs(Input::new("$ cd ..\n")).is_err());
+    }
 }
@@ -1,106 +1,200 @@
+use std::fmt::Write;
+
 use advent_of_code::helpers::Input;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 
 pub fn part_one(input: Input) -> Result<u32> {
-    let grid = parse_grid(input)?;
-    let mut counted = vec![vec![false; grid[0].len()]; grid.len()];
-    let mut count = (2 * grid.len() + 2 * (grid[0].len() - 2)) as u32; // all outer trees visible
-
-    // left, right
-    for i in 1..(grid[0].len() - 1) {
-        count += visible_in_dir(&grid, &mut counted, 0, i, [1, 0]);
-        count += visible_in_dir(&grid, &mut counted, grid.len() - 1, i, [-1, 0]);
-    }
-    // up, down
-    for i in 1..(grid.len() - 1) {
-        count += visible_in_dir(&grid, &mut counted, i, 0, [0, 1]);
-        count += visible_in_dir(&grid, &mut counted, i, grid[0].len() - 1, [0, -1]);
-    }
-
-    Ok(count)
+    let forest = Forest::analyze(&parse_grid(input)?);
+    Ok(forest.visible.iter().filter(|v| **v).count() as u32)
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
-    let grid = parse_grid(input)?;
-    let mut max_score = 0;
-    for i in 0..grid.len() {
-        for j in 0..grid[0].len() {
-            let score = score_tree(&grid, i, j);
-            if score > max_score {
-                max_score = score;
-            }
-        }
-    }
-    Ok(max_score)
+    let forest = Forest::analyze(&parse_grid(input)?);
+    forest.scenic.iter().copied().max().context("empty grid")
 }
 
-fn visible_in_dir(
-    grid: &[Vec<u32>],
-    counted: &mut [Vec<bool>],
-    i: usize,
-    j: usize,
-    dir: [i32; 2],
-) -> u32 {
-    let mut max_height = grid[i][j];
-    let mut count = 0;
-    let (mut x, mut y) = move_dir(dir, i, j);
-
-    while x > 0 && x < (grid.len() - 1) && y > 0 && y < (grid[0].len() - 1) {
-        if grid[x][y] > max_height {
-            max_height = grid[x][y];
-            if !counted[x][y] {
-                count += 1;
-            }
-            counted[x][y] = true;
-        }
-        (x, y) = move_dir(dir, x, y);
-    }
+struct Grid {
+    rows: usize,
+    cols: usize,
+    heights: Vec<u8>,
+}
 
-    count
+/// Per-tree results, row-major like the grid.
+struct Forest {
+    cols: usize,
+    visible: Vec<bool>,
+    scenic: Vec<u32>,
 }
 
-fn score_tree(grid: &[Vec<u32>], i: usize, j: usize) -> u32 {
-    let dirs = [[0, 1], [0, -1], [1, 0], [-1, 0]];
-    let mut score = 1;
-    let tree_height = grid[i][j];
+impl Forest {
+    /// Sweeps every row and column once in each direction. A stack holds the positions of the
+    /// trees still blocking the view, strictly decreasing in height: after popping the ones
+    /// lower than the current tree, the top is the first tree blocking its view, and an empty
+    /// stack means it's visible from that edge.
+    fn analyze(grid: &Grid) -> Self {
+        let (rows, cols) = (grid.rows, grid.cols);
+        let mut visible = vec![false; grid.heights.len()];
+        let mut scenic = vec![1; grid.heights.len()];
 
-    for dir in dirs {
-        let mut dir_score = 1;
-        let (mut x, mut y) = move_dir(dir, i, j);
+        let row = |r: usize| (0..cols).map(move |c| r * cols + c);
+        let col = |c: usize| (0..rows).map(move |r| r * cols + c);
+        let mut lines: Vec<Vec<usize>> = vec![];
+        for r in 0..rows {
+            lines.push(row(r).collect());
+            lines.push(row(r).rev().collect());
+        }
+        for c in 0..cols {
+            lines.push(col(c).collect());
+            lines.push(col(c).rev().collect());
+        }
 
-        while x > 0 && x < (grid.len() - 1) && y > 0 && y < (grid[0].len() - 1) {
-            if grid[x][y] < tree_height {
-                dir_score += 1;
-            } else {
-                break;
+        let mut stack: Vec<usize> = vec![];
+        for line in &lines {
+            stack.clear();
+            for (pos, &idx) in line.iter().enumerate() {
+                let height = grid.heights[idx];
+                while stack
+                    .last()
+                    .is_some_and(|&p| grid.heights[line[p]] < height)
+                {
+                    stack.pop();
+                }
+                let distance = match stack.last() {
+                    Some(&p) => pos - p,
+                    None => {
+                        visible[idx] = true;
+                        pos
+                    }
+                };
+                scenic[idx] *= distance as u32;
+                // a tree of the same height blocks everything behind it just as well.
+                if stack
+                    .last()
+                    .is_some_and(|&p| grid.heights[line[p]] == height)
+                {
+                    stack.pop();
+                }
+                stack.push(pos);
             }
-            (x, y) = move_dir(dir, x, y);
         }
 
-        score *= dir_score;
+        Self {
+            cols,
+            visible,
+            scenic,
+        }
     }
+}
 
-    score
+/// Renders row-major values as a plain (ASCII) PGM image, from black for 0 to white for `max`.
+fn to_pgm(values: &[u32], cols: usize, max: u32) -> String {
+    // scenic scores easily exceed the largest maxval a PGM may have, so always scale.
+    const MAXVAL: u64 = 255;
+    let rows = values.len() / cols;
+    let mut out = format!("P2\n{} {}\n{}\n", cols, rows, MAXVAL);
+    for row in values.chunks(cols) {
+        let row = row
+            .iter()
+            .map(|&v| (v as u64 * MAXVAL / max.max(1) as u64).to_string())
+            .collect::<Vec<_>>();
+        writeln!(out, "{}", row.join(" ")).unwrap();
+    }
+    out
 }
 
-fn move_dir(dir: [i32; 2], i: usize, j: usize) -> (usize, usize) {
-    ((i as i32 + dir[0]) as usize, (j as i32 + dir[1]) as usize)
+/// Renders row-major values as ASCII shading, from ` ` for 0 to `@` for `max`.
+fn to_ascii(values: &[u32], cols: usize, max: u32) -> String {
+    const SHADES: &[u8] = b" .:-=+*#%@";
+    let mut out = String::new();
+    for row in values.chunks(cols) {
+        for v in row {
+            let shade = *v as usize * (SHADES.len() - 1) / max.max(1) as usize;
+            out.push(SHADES[shade] as char);
+        }
+        out.push('\n');
+    }
+    out
 }
 
-fn parse_grid(input: Input) -> Result<Vec<Vec<u32>>> {
-    let mut grid = Vec::new();
-    for line in input.as_str().lines() {
-        let mut row = Vec::new();
+fn parse_grid(input: Input) -> Result<Grid> {
+    let mut heights = Vec::new();
+    let mut cols = None;
+    let mut rows = 0;
+    for (n, line) in input.as_str().lines().enumerate() {
+        let len = line.chars().count();
+        if *cols.get_or_insert(len) != len {
+            return Err(anyhow!(
+                "row {} has {} trees, expected {}",
+                n + 1,
+                len,
+                cols.unwrap_or_default()
+            ));
+        }
         for c in line.chars() {
-            row.push(c.to_digit(10).ok_or_else(|| anyhow!("Invalid digit"))?);
+            let h = c
+                .to_digit(10)
+                .with_context(|| format!("row {}: invalid digit {:?}", n + 1, c))?;
+            heights.push(h as u8);
         }
-        grid.push(row);
+        rows += 1;
+    }
+    match cols {
+        Some(cols) if cols > 0 => Ok(Grid {
+            rows,
+            cols,
+            heights,
+        }),
+        _ => Err(anyhow!("empty grid")),
     }
-    Ok(grid)
+}
+
+/// Renders the scenic scores or the visibility mask of the input.
+fn export(input: Input, map: &str, format: &str) -> Result<String> {
+    let forest = Forest::analyze(&parse_grid(input)?);
+    let values = match map {
+        "scenic" => forest.scenic,
+        "visible" => forest.visible.iter().map(|v| *v as u32).collect(),
+        _ => return Err(anyhow!("unknown map {:?}, expected scenic or visible", map)),
+    };
+    let max = values.iter().copied().max().unwrap_or_default();
+    match format {
+        "pgm" => Ok(to_pgm(&values, forest.cols, max)),
+        "ascii" => Ok(to_ascii(&values, forest.cols, max)),
+        _ => Err(anyhow!(
+            "unknown format {:?}, expected pgm or ascii",
+            format
+        )),
+    }
+}
+
+struct Args {
+    export: Option<String>,
+    format: String,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        export: args.opt_value_from_str("--export")?,
+        format: args
+            .opt_value_from_str("--format")?
+            .unwrap_or_else(|| "ascii".to_string()),
+    })
 }
 
 fn main() {
     let input = &advent_of_code::read_file("inputs", 8);
+
+    // `--export scenic|visible [--format ascii|pgm]` prints a map of the forest instead.
+    let args = parse_args().expect("invalid arguments");
+    if let Some(map) = args.export {
+        match export(Input::new(input), &map, &args.format) {
+            Ok(r) => print!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -120,4 +214,43 @@ mod tests {
         let input = advent_of_code::read_file("examples", 8);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 8);
     }
+
+    #[test]
+    fn test_narrow_grids() {
+        assert_eq!(part_one(Input::new("30373\n")).unwrap(), 5);
+        assert_eq!(part_two(Input::new("30373\n")).unwrap(), 0);
+        assert_eq!(part_one(Input::new("3\n0\n3\n")).unwrap(), 3);
+        assert_eq!(part_one(Input::new("5\n")).unwrap(), 1);
+        assert!(part_one(Input::new("303\n12\n")).is_err());
+        assert!(part_one(Input::new("")).is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let input = advent_of_code::read_file("examples", 8);
+        assert_eq!(
+            export(Input::new(&input), "visible", "ascii").unwrap(),
+            "@@@@@\n@@@ @\n@@ @@\n@ @ @\n@@@@@\n"
+        );
+        let pgm = export(Input::new(&input), "scenic", "pgm").unwrap();
+        assert!(pgm.starts_with("P2\n5 5\n255\n0 0 0 0 0\n0 31 127 31 0\n"));
+
+        // a lone tall tree in the middle of a 35x35 forest sees 17 trees in every direction.
+        let mut rows = vec!["0".repeat(35); 35];
+        rows[17].replace_range(17..18, "9");
+        let input = rows.join("\n") + "\n";
+        assert_eq!(part_two(Input::new(&input)).unwrap(), 17u32.pow(4));
+        let pgm = export(Input::new(&input), "scenic", "pgm").unwrap();
+        let mut lines = pgm.lines();
+        assert_eq!(
+            lines.by_ref().take(3).collect::<Vec<_>>(),
+            ["P2", "35 35", "255"]
+        );
+        let pixels = lines
+            .flat_map(str::split_whitespace)
+            .map(|p| p.parse::<u32>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(pixels.len(), 35 * 35);
+        assert_eq!(pixels.iter().max(), Some(&255));
+    }
 }
@@ -1,103 +1,347 @@
 use advent_of_code::helpers::Input;
 use anyhow::{anyhow, Context, Result};
-use itertools::{enumerate, Itertools};
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+/// A position as `[row, col]`, with rows growing upwards.
+type Pos = [i32; 2];
 
 lazy_static! {
-    static ref DIRS: HashMap<&'static str, [i32; 2]> =
-        HashMap::from([("R", [0, 1]), ("L", [0, -1]), ("U", [1, 0]), ("D", [-1, 0])]);
+    static ref DIRS: HashMap<&'static str, Pos> = HashMap::from([
+        ("R", [0, 1]),
+        ("L", [0, -1]),
+        ("U", [1, 0]),
+        ("D", [-1, 0]),
+        ("UR", [1, 1]),
+        ("UL", [1, -1]),
+        ("DR", [-1, 1]),
+        ("DL", [-1, -1]),
+    ]);
 }
 
 pub fn part_one(input: Input) -> Result<u32> {
-    simulate_rope(input, 2, false)
+    simulate_rope(input, 2)
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
-    simulate_rope(input, 10, false)
+    simulate_rope(input, 10)
 }
 
-fn simulate_rope(input: Input, num_knots: usize, print: bool) -> Result<u32> {
-    let steps = parse_input(input)?;
-    let mut tail_locs = HashSet::<[i32; 2]>::new();
-
-    let mut knots = vec![[0; 2]; num_knots];
+fn simulate_rope(input: Input, num_knots: usize) -> Result<u32> {
+    let rope = simulate(&parse_input(input)?, Rope::new(num_knots, false)?, |_| {
+        Ok(())
+    })?;
+    Ok(rope.tail_visited.len() as u32)
+}
 
-    for (dir, count) in steps {
+/// Moves the head of `rope` one step at a time and calls `on_step` after every step.
+fn simulate(
+    steps: &[(Pos, usize)],
+    mut rope: Rope,
+    mut on_step: impl FnMut(&Rope) -> Result<()>,
+) -> Result<Rope> {
+    for &(dir, count) in steps {
         for _ in 0..count {
-            // move head
-            knots[0][0] += dir[0];
-            knots[0][1] += dir[1];
+            rope.step(dir);
+            on_step(&rope)?;
+        }
+    }
+    Ok(rope)
+}
 
-            // knots follow
-            for i in 1..knots.len() {
-                move_knot(&mut knots, i);
-            }
+struct Rope {
+    knots: Vec<Pos>,
+    tail_visited: HashSet<Pos>,
+    /// every position of every knot, starting at the origin. Grows with every step, so it is
+    /// only kept on request.
+    history: Option<Vec<Vec<Pos>>>,
+}
 
-            tail_locs.insert(*knots.last().context(anyhow!("No tail"))?);
+impl Rope {
+    fn new(num_knots: usize, keep_history: bool) -> Result<Self> {
+        if num_knots == 0 {
+            return Err(anyhow!("a rope needs at least one knot"));
+        }
+        Ok(Self {
+            knots: vec![[0; 2]; num_knots],
+            tail_visited: HashSet::from([[0; 2]]),
+            history: keep_history.then(|| vec![vec![[0; 2]]; num_knots]),
+        })
+    }
+
+    fn step(&mut self, dir: Pos) {
+        self.knots[0][0] += dir[0];
+        self.knots[0][1] += dir[1];
 
-            if print {
-                print_rope(&knots);
+        // knots follow
+        for i in 1..self.knots.len() {
+            move_knot(&mut self.knots, i);
+        }
+        if let Some(history) = &mut self.history {
+            for (history, knot) in history.iter_mut().zip(&self.knots) {
+                history.push(*knot);
             }
         }
+        self.tail_visited.insert(*self.tail());
     }
 
-    Ok(tail_locs.len() as u32)
+    fn tail(&self) -> &Pos {
+        self.knots.last().unwrap()
+    }
 }
 
-fn move_knot(knots: &mut [[i32; 2]], i: usize) {
+fn move_knot(knots: &mut [Pos], i: usize) {
     let dist = knot_dist(&knots[i - 1], &knots[i]);
-    if dist[0].abs() == 2 || dist[1].abs() == 2 {
-        knots[i][0] += move_towards(dist[0]);
-        knots[i][1] += move_towards(dist[1]);
+    if dist[0].abs() >= 2 || dist[1].abs() >= 2 {
+        knots[i][0] += dist[0].signum();
+        knots[i][1] += dist[1].signum();
     }
 }
 
-fn knot_dist(a: &[i32; 2], b: &[i32; 2]) -> [i32; 2] {
+fn knot_dist(a: &Pos, b: &Pos) -> Pos {
     [a[0] - b[0], a[1] - b[1]]
 }
 
-fn move_towards(dist: i32) -> i32 {
-    match dist.cmp(&0) {
-        std::cmp::Ordering::Less => -1,
-        std::cmp::Ordering::Equal => 0,
-        std::cmp::Ordering::Greater => 1,
-    }
-}
-
-fn parse_input(input: Input) -> Result<Vec<([i32; 2], usize)>> {
+fn parse_input(input: Input) -> Result<Vec<(Pos, usize)>> {
     input
         .as_str()
         .lines()
-        .map(|l| {
-            let (d, c) = l
-                .split_whitespace()
-                .collect_tuple()
-                .ok_or_else(|| anyhow!("Invalid input"))?;
-            let dir = DIRS.get(d).ok_or_else(|| anyhow!("Invalid direction"))?;
-            let count = c.parse::<usize>()?;
-            Ok((*dir, count))
+        .enumerate()
+        .map(|(n, l)| {
+            let parse = || -> Result<(Pos, usize)> {
+                let (d, c) = l
+                    .split_whitespace()
+                    .collect_tuple()
+                    .context("expected a direction and a step count")?;
+                let dir = DIRS
+                    .get(d)
+                    .with_context(|| format!("invalid direction {:?}", d))?;
+                let count = c
+                    .parse::<usize>()
+                    .with_context(|| format!("invalid step count {:?}", c))?;
+                Ok((*dir, count))
+            };
+            parse().with_context(|| format!("line {}: {:?}", n + 1, l))
         })
         .collect()
 }
 
-fn print_rope(knots: &[[i32; 2]]) {
-    let mut grid = vec![vec![".".to_owned(); 6]; 5];
-    for (i, knot) in enumerate(knots).rev() {
-        grid[knot[0] as usize][knot[1] as usize] = if i == 0 {
-            "H".to_string()
-        } else {
-            i.to_string()
+/// The rows and columns drawn in a frame. It grows to fit the rope but never shrinks, so the
+/// picture doesn't jump around while the rope moves back and forth.
+struct Viewport {
+    min: Pos,
+    max: Pos,
+}
+
+impl Viewport {
+    fn new() -> Self {
+        Self {
+            min: [0; 2],
+            max: [0; 2],
+        }
+    }
+
+    fn include(&mut self, p: &Pos) {
+        self.min = [self.min[0].min(p[0]), self.min[1].min(p[1])];
+        self.max = [self.max[0].max(p[0]), self.max[1].max(p[1])];
+    }
+
+    /// Draws the rope like the puzzle description does: `H` for the head, the knot number for
+    /// the others, `s` for the start and `#` for positions the tail visited.
+    fn render(&self, rope: &Rope) -> Vec<Vec<char>> {
+        let rows = (self.max[0] - self.min[0] + 1) as usize;
+        let cols = (self.max[1] - self.min[1] + 1) as usize;
+        let mut grid = vec![vec!['.'; cols]; rows];
+        let mut draw = |p: &Pos, c: char| {
+            grid[(self.max[0] - p[0]) as usize][(p[1] - self.min[1]) as usize] = c;
         };
+
+        for p in &rope.tail_visited {
+            draw(p, '#');
+        }
+        draw(&[0; 2], 's');
+        for (i, knot) in rope.knots.iter().enumerate().rev() {
+            let c = match i {
+                0 => 'H',
+                1..=9 => char::from_digit(i as u32, 10).unwrap(),
+                _ => '+',
+            };
+            draw(knot, c);
+        }
+        grid
     }
-    for row in grid.iter().rev() {
-        println!("{}", row.join(""));
+}
+
+/// Where animation frames go.
+enum FrameSink {
+    /// redraws every frame in place, waiting `delay` in between.
+    Terminal { delay: Duration },
+    /// appends all frames to one text file, separated by form feeds.
+    Text(BufWriter<File>),
+    /// writes one PGM image per frame into a directory.
+    Images(PathBuf),
+}
+
+impl FrameSink {
+    /// Each cell becomes a square of this many pixels in images.
+    const CELL_PIXELS: usize = 4;
+
+    fn new(kind: &str, out: Option<PathBuf>, delay: Duration) -> Result<Self> {
+        match (kind, out) {
+            ("terminal", _) => Ok(FrameSink::Terminal { delay }),
+            ("text", Some(path)) => {
+                let file =
+                    File::create(&path).with_context(|| format!("could not create {:?}", path))?;
+                Ok(FrameSink::Text(BufWriter::new(file)))
+            }
+            ("images", Some(dir)) => {
+                fs::create_dir_all(&dir).with_context(|| format!("could not create {:?}", dir))?;
+                Ok(FrameSink::Images(dir))
+            }
+            ("text" | "images", None) => Err(anyhow!("--animate {} needs --out <path>", kind)),
+            _ => Err(anyhow!(
+                "unknown animation output {:?}, expected terminal, text or images",
+                kind
+            )),
+        }
     }
-    println!();
+
+    fn emit(&mut self, frame: usize, grid: &[Vec<char>]) -> Result<()> {
+        match self {
+            FrameSink::Terminal { delay } => {
+                print!("\x1b[2J\x1b[H{}\nframe {}\n", to_text(grid), frame);
+                thread::sleep(*delay);
+            }
+            FrameSink::Text(w) => write!(w, "{}\n\x0c\n", to_text(grid))?,
+            FrameSink::Images(dir) => {
+                let path = dir.join(format!("frame_{:05}.pgm", frame));
+                fs::write(&path, to_pgm(grid, Self::CELL_PIXELS))
+                    .with_context(|| format!("could not write {:?}", path))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        if let FrameSink::Text(mut w) = self {
+            w.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn to_text(grid: &[Vec<char>]) -> String {
+    grid.iter()
+        .map(|row| row.iter().collect::<String>())
+        .join("\n")
+}
+
+/// Renders a frame as a plain PGM image, knots dark and empty cells white.
+fn to_pgm(grid: &[Vec<char>], scale: usize) -> String {
+    let rows = grid.len() * scale;
+    let cols = grid.first().map_or(0, Vec::len) * scale;
+    let mut out = format!("P2\n{} {}\n255\n", cols, rows);
+    for row in grid {
+        let line = row
+            .iter()
+            .flat_map(|c| {
+                let shade = match c {
+                    '.' => 255,
+                    '#' => 200,
+                    's' => 150,
+                    'H' => 0,
+                    _ => 80,
+                };
+                std::iter::repeat_n(shade, scale)
+            })
+            .join(" ");
+        for _ in 0..scale {
+            out.push_str(&line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Simulates the rope and sends a frame to `sink` after every step.
+fn animate(input: Input, num_knots: usize, mut sink: FrameSink) -> Result<()> {
+    let mut viewport = Viewport::new();
+    let mut frame = 0;
+    simulate(&parse_input(input)?, Rope::new(num_knots, false)?, |rope| {
+        rope.knots.iter().for_each(|k| viewport.include(k));
+        frame += 1;
+        sink.emit(frame, &viewport.render(rope))
+    })?;
+    sink.finish()
+}
+
+/// Every position of knot `knot` (0 is the head) as `row,col` lines, starting at the origin.
+fn knot_history(input: Input, num_knots: usize, knot: usize) -> Result<String> {
+    let rope = simulate(
+        &parse_input(input)?,
+        Rope::new(num_knots, true)?,
+        |_| Ok(()),
+    )?;
+    let history = rope.history.unwrap_or_default();
+    let positions = history
+        .get(knot)
+        .with_context(|| format!("knot {} does not exist in a rope of {}", knot, num_knots))?;
+    Ok(positions
+        .iter()
+        .map(|p| format!("{},{}\n", p[0], p[1]))
+        .collect())
+}
+
+struct Args {
+    animate: Option<String>,
+    history: Option<usize>,
+    knots: usize,
+    out: Option<PathBuf>,
+    delay: u64,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        animate: args.opt_value_from_str("--animate")?,
+        history: args.opt_value_from_str("--history")?,
+        knots: args.opt_value_from_str("--knots")?.unwrap_or(10),
+        out: args.opt_value_from_str("--out")?,
+        delay: args.opt_value_from_str("--delay")?.unwrap_or(50),
+    })
 }
 
 fn main() {
     let input = &advent_of_code::read_file("inputs", 9);
+
+    // `--animate terminal|text|images [--knots <n>] [--out <path>] [--delay <ms>]` draws a
+    // frame after every step of the rope.
+    let args = parse_args().expect("invalid arguments");
+    if let Some(kind) = args.animate {
+        let delay = Duration::from_millis(args.delay);
+        let res = FrameSink::new(&kind, args.out, delay)
+            .and_then(|sink| animate(Input::new(input), args.knots, sink));
+        if let Err(e) = res {
+            eprintln!("{:#}", e);
+        }
+        return;
+    }
+
+    // `--history <knot> [--knots <n>]` prints every position of one knot, 0 being the head.
+    if let Some(knot) = args.history {
+        match knot_history(Input::new(input), args.knots, knot) {
+            Ok(r) => print!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -117,4 +361,46 @@ mod tests {
         let input = advent_of_code::read_file("examples", 9);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 36);
     }
+
+    #[test]
+    fn test_render() {
+        let steps = parse_input(Input::new("R 4\nU 4\n")).unwrap();
+        let mut viewport = Viewport::new();
+        let rope = simulate(&steps, Rope::new(10, true).unwrap(), |rope| {
+            rope.knots.iter().for_each(|k| viewport.include(k));
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            to_text(&viewport.render(&rope)),
+            "....H\n....1\n..432\n.5...\n6....",
+        );
+        let history = rope.history.unwrap();
+        assert_eq!(history.len(), 10);
+        assert_eq!(history[0].len(), 9);
+        assert_eq!(history[1][4], [0, 3]);
+        assert!(history.iter().all(|h| h[0] == [0; 2]));
+
+        let rope = simulate(&steps, Rope::new(10, false).unwrap(), |_| Ok(())).unwrap();
+        assert!(rope.history.is_none());
+        assert_eq!(
+            knot_history(Input::new("R 4\nU 4\n"), 2, 1).unwrap(),
+            "0,0\n0,0\n0,1\n0,2\n0,3\n0,3\n1,4\n2,4\n3,4\n"
+        );
+        assert!(knot_history(Input::new("R 4\n"), 2, 2).is_err());
+    }
+
+    #[test]
+    fn test_diagonal_and_invalid_input() {
+        let steps = parse_input(Input::new("UR 3\nDL 1\n")).unwrap();
+        let rope = simulate(&steps, Rope::new(3, false).unwrap(), |_| Ok(())).unwrap();
+        assert_eq!(rope.knots, vec![[2, 2], [2, 2], [1, 1]]);
+
+        let err = parse_input(Input::new("R 4\nX 2\n")).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "line 2: \"X 2\": invalid direction \"X\""
+        );
+        assert!(Rope::new(0, false).is_err());
+    }
 }
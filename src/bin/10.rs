@@ -1,13 +1,14 @@
+use std::fmt;
 use std::str::FromStr;
 
-use advent_of_code::helpers::Input;
-use anyhow::{anyhow, Result};
+use advent_of_code::helpers::{ocr, Input};
+use anyhow::{anyhow, Context, Result};
 use itertools::Itertools;
 
 pub fn part_one(input: Input) -> Result<i32> {
-    let screen = Screen::new(parse_program(input)?);
+    let cpu = Cpu::new(parse_program(input)?);
     let mut res = 0_i32;
-    for (i, x) in screen.enumerate() {
+    for (i, x) in cpu.enumerate() {
         let cycle = i + 1;
         if cycle >= 20 && (cycle - 20) % 40 == 0 {
             res += x * cycle as i32;
@@ -16,96 +17,147 @@ pub fn part_one(input: Input) -> Result<i32> {
     Ok(res)
 }
 
-pub fn part_two(input: Input) -> Result<i32> {
-    let screen = Screen::new(parse_program(input)?);
-    let mut pixels = vec![['.'; 40]; 1];
-    for (i, x) in screen.enumerate() {
-        // i == cycle - 1
-        let pos = i % 40;
-        if (pos as i32 - x).abs() < 2 {
-            while pixels.len() < i / 40 + 1 {
-                pixels.push(['.'; 40]);
-            }
-
-            pixels[i / 40][pos] = '#';
-        }
-    }
+pub fn part_two(input: Input) -> Result<String> {
+    let crt = run_crt(input)?;
+    crt.read()
+        .with_context(|| format!("could not read the screen:\n{}", crt))
+}
 
-    for row in pixels {
-        println!("{}", row.iter().join(""));
+fn run_crt(input: Input) -> Result<Crt> {
+    let mut crt = Crt::new(40, 6);
+    for (cycle, x) in Cpu::new(parse_program(input)?).enumerate() {
+        crt.draw(cycle, x);
     }
-
-    Ok(0)
+    Ok(crt)
 }
 
 fn parse_program(input: Input) -> Result<Vec<Opcode>> {
-    input.as_str().lines().map(|s| s.parse()).collect()
+    input
+        .as_str()
+        .lines()
+        .enumerate()
+        .map(|(n, s)| {
+            s.parse()
+                .with_context(|| format!("line {}: {:?}", n + 1, s))
+        })
+        .collect()
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 enum Opcode {
     Noop,
     Addx(i32),
 }
 
+impl Opcode {
+    /// How many cycles the instruction takes. Its effect is only visible after the last one.
+    fn cycles(&self) -> usize {
+        match self {
+            Opcode::Noop => 1,
+            Opcode::Addx(_) => 2,
+        }
+    }
+
+    fn execute(&self, regs: &mut Registers) {
+        match self {
+            Opcode::Noop => {}
+            Opcode::Addx(v) => regs.x += v,
+        }
+    }
+}
+
 impl FromStr for Opcode {
     type Err = anyhow::Error;
 
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         let parts = s.split_whitespace().collect_vec();
-        match parts[0] {
-            "noop" => Ok(Opcode::Noop),
-            "addx" => {
-                let x = parts[1].parse()?;
-                Ok(Opcode::Addx(x))
-            }
-            _ => Err(anyhow!("Unknown instruction: {}", parts[0])),
+        match parts[..] {
+            ["noop"] => Ok(Opcode::Noop),
+            ["addx", v] => Ok(Opcode::Addx(v.parse()?)),
+            [op, ..] => Err(anyhow!("unknown instruction {:?}", op)),
+            [] => Err(anyhow!("empty line")),
         }
     }
 }
 
-struct Screen {
-    program: Vec<Opcode>,
-    cntr: usize,
+struct Registers {
     x: i32,
-    stalled: bool,
-    inst_idx: usize,
 }
 
-impl Screen {
+/// Runs a program and yields the value of `x` during every cycle.
+struct Cpu {
+    program: Vec<Opcode>,
+    regs: Registers,
+    pc: usize,
+    /// cycles the instruction at `pc` has been running.
+    busy: usize,
+}
+
+impl Cpu {
     fn new(program: Vec<Opcode>) -> Self {
         Self {
             program,
-            cntr: 0,
-            x: 1,
-            stalled: false,
-            inst_idx: 0,
+            regs: Registers { x: 1 },
+            pc: 0,
+            busy: 0,
         }
     }
 }
 
-impl Iterator for Screen {
+impl Iterator for Cpu {
     type Item = i32;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.inst_idx >= self.program.len() {
-            return None;
+        let op = self.program.get(self.pc)?;
+        let x_during = self.regs.x;
+
+        self.busy += 1;
+        if self.busy == op.cycles() {
+            op.execute(&mut self.regs);
+            self.pc += 1;
+            self.busy = 0;
         }
 
-        let x_before = self.x;
-        self.cntr += 1;
-        match self.program[self.inst_idx] {
-            Opcode::Noop => self.inst_idx += 1,
-            Opcode::Addx(v) => {
-                if self.stalled {
-                    self.x += v;
-                    self.inst_idx += 1;
-                }
-                self.stalled = !self.stalled;
-            }
-        };
+        Some(x_during)
+    }
+}
+
+/// A framebuffer the CRT beam draws into one pixel per cycle, left to right and top to bottom.
+struct Crt {
+    width: usize,
+    height: usize,
+    pixels: Vec<bool>,
+}
+
+impl Crt {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![false; width * height],
+        }
+    }
+
+    /// Lights the pixel drawn during `cycle` (counting from 0) if the three pixel wide sprite
+    /// centered at column `sprite` covers it. Cycles past the last pixel wrap around.
+    fn draw(&mut self, cycle: usize, sprite: i32) {
+        let pos = cycle % (self.width * self.height);
+        let col = (pos % self.width) as i32;
+        self.pixels[pos] = (col - sprite).abs() < 2;
+    }
 
-        Some(x_before)
+    fn read(&self) -> Result<String, ocr::OcrError> {
+        ocr::read_letters(&self.pixels, self.width)
+    }
+}
+
+impl fmt::Display for Crt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.pixels.chunks(self.width) {
+            let row: String = row.iter().map(|&p| if p { '#' } else { '.' }).collect();
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
     }
 }
 
@@ -128,6 +180,28 @@ mod tests {
     #[test]
     fn test_part_two() {
         let input = advent_of_code::read_file("examples", 10);
-        assert!(part_two(Input::new(&input)).is_ok());
+        let crt = run_crt(Input::new(&input)).unwrap();
+        assert_eq!(
+            crt.to_string(),
+            "##..##..##..##..##..##..##..##..##..##..\n\
+             ###...###...###...###...###...###...###.\n\
+             ####....####....####....####....####....\n\
+             #####.....#####.....#####.....#####.....\n\
+             ######......######......######......####\n\
+             #######.......#######.......#######.....\n"
+        );
+        // the example draws stripes instead of letters.
+        assert!(part_two(Input::new(&input)).is_err());
+    }
+
+    #[test]
+    fn test_read_letters() {
+        let picture = "#..#..###.#..#...#..####...#..#..#...#..#..#...#..#..#..###.";
+        let crt = Crt {
+            width: 10,
+            height: 6,
+            pixels: picture.chars().map(|c| c == '#').collect(),
+        };
+        assert_eq!(crt.read().unwrap(), "HI");
     }
 }
@@ -1,7 +1,12 @@
-use std::{collections::VecDeque, str::FromStr};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt,
+    str::FromStr,
+};
 
 use advent_of_code::helpers::Input;
 use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
 
 pub fn part_one(input: Input) -> Result<u64> {
     monkey_business(input, 20, 3)
@@ -13,48 +18,325 @@ pub fn part_two(input: Input) -> Result<u64> {
 
 fn monkey_business(input: Input, num_rounds: usize, worry_divisor: u64) -> Result<u64> {
     let mut monkeys = parse_monkeys(input)?;
-    let mut num_inspections = run_rounds(&mut monkeys, num_rounds, worry_divisor)?;
+    let mut num_inspections = run_rounds(&mut monkeys, num_rounds, worry_divisor, &mut ())?;
     num_inspections.sort();
     Ok(num_inspections[num_inspections.len() - 1] * num_inspections[num_inspections.len() - 2])
 }
 
 fn run_rounds(
-    monkeys: &mut Vec<Monkey>,
+    monkeys: &mut [Monkey],
     num_rounds: usize,
     worry_divisor: u64,
+    observer: &mut impl Observer,
 ) -> Result<Vec<u64>> {
     // trick to keep the worry score from overflowing while still maintaining the
     // modular arithmetic
-    let common_divisor: u64 = monkeys.iter().map(|m| m.divisor).product();
+    let modulus = common_divisor(monkeys, worry_divisor);
     let mut num_inspections = vec![0; monkeys.len()];
-    for _ in 0..num_rounds {
+    for round in 1..=num_rounds {
         for m in 0..monkeys.len() {
             while !monkeys[m].is_empty() {
-                let (new_worry, to) = monkeys[m].throw(worry_divisor)?;
+                let (old, new, to) = monkeys[m]
+                    .throw(worry_divisor)
+                    .with_context(|| format!("round {}, monkey {}", round, m))?;
                 num_inspections[m] += 1;
-                monkeys[to].items.push_back(new_worry % common_divisor);
+                let new = modulus.map_or(new, |d| new % d);
+                monkeys
+                    .get_mut(to)
+                    .with_context(|| format!("monkey {} throws to missing monkey {}", m, to))?
+                    .items
+                    .push_back(new);
+                observer.on_throw(&Throw {
+                    round,
+                    from: m,
+                    to,
+                    old,
+                    new,
+                });
             }
         }
+        observer.on_round(round, monkeys, &num_inspections)?;
     }
     Ok(num_inspections)
 }
 
+/// An item changing hands. `new` is the worry level the receiving monkey holds it with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Throw {
+    round: usize,
+    from: usize,
+    to: usize,
+    old: u64,
+    new: u64,
+}
+
+/// Watches the simulation, e.g. to trace it.
+trait Observer {
+    fn on_throw(&mut self, _throw: &Throw) {}
+
+    /// Called with the monkeys and the inspection counts so far after every round. An error
+    /// stops the simulation.
+    fn on_round(&mut self, _round: usize, _monkeys: &[Monkey], _inspections: &[u64]) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Observer for () {}
+
+/// Prints the state after every round the way the puzzle description does.
+struct RoundDump<W> {
+    out: W,
+}
+
+impl<W: std::io::Write> Observer for RoundDump<W> {
+    fn on_round(&mut self, round: usize, monkeys: &[Monkey], inspections: &[u64]) -> Result<()> {
+        let mut dump = format!(
+            "After round {}, the monkeys are holding items with these worry levels:\n",
+            round
+        );
+        for (m, monkey) in monkeys.iter().enumerate() {
+            dump += &format!("Monkey {}: {}\n", m, monkey.items.iter().join(", "));
+        }
+        dump += "\n";
+        for (m, count) in inspections.iter().enumerate() {
+            dump += &format!("Monkey {} inspected items {} times.\n", m, count);
+        }
+        writeln!(self.out, "{}", dump).context("could not write round dump")
+    }
+}
+
+/// Counts the throws between every pair of monkeys.
+#[derive(Default)]
+struct ThrowGraph {
+    edges: BTreeMap<(usize, usize), u64>,
+}
+
+impl Observer for ThrowGraph {
+    fn on_throw(&mut self, throw: &Throw) {
+        *self.edges.entry((throw.from, throw.to)).or_default() += 1;
+    }
+}
+
+impl ThrowGraph {
+    /// Renders the graph in Graphviz DOT format, edges labelled with their throw counts.
+    fn to_dot(&self) -> String {
+        let mut dot = "digraph throws {\n".to_string();
+        for ((from, to), count) in &self.edges {
+            dot += &format!("    {} -> {} [label=\"{}\"];\n", from, to, count);
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+/// The product of all test divisors, if worry levels can be reduced modulo it without changing
+/// the outcome of any test. That holds when the operations only add and multiply, since those
+/// preserve remainders, and worry levels aren't divided after them.
+fn common_divisor(monkeys: &[Monkey], worry_divisor: u64) -> Option<u64> {
+    let sound = worry_divisor == 1 && monkeys.iter().all(|m| m.op.preserves_remainders());
+    sound.then(|| monkeys.iter().map(|m| m.test.divisor).product())
+}
+
 fn parse_monkeys(input: Input) -> Result<Vec<Monkey>> {
     input
         .as_str()
         .split("\n\n")
-        .map(|g| g.parse())
+        .enumerate()
+        .map(|(n, g)| g.parse().with_context(|| format!("monkey {}", n)))
         .collect::<Result<_>>()
 }
 
-type OpFunc = Box<dyn Fn(u64) -> u64>;
-type TestFunc = Box<dyn Fn(u64) -> usize>;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+}
 
+impl BinOp {
+    fn apply(self, a: u64, b: u64) -> Option<u64> {
+        match self {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            BinOp::Mul => a.checked_mul(b),
+        }
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+        };
+        write!(f, "{}", op)
+    }
+}
+
+/// The right hand side of a monkey's operation, e.g. `old * 19`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Old,
+    Lit(u64),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+}
+
+impl Expr {
+    /// Evaluates the expression for the worry level `old`, `None` if it over- or underflows.
+    fn eval(&self, old: u64) -> Option<u64> {
+        match self {
+            Expr::Old => Some(old),
+            Expr::Lit(v) => Some(*v),
+            Expr::Binary(a, op, b) => op.apply(a.eval(old)?, b.eval(old)?),
+        }
+    }
+
+    fn preserves_remainders(&self) -> bool {
+        match self {
+            Expr::Old | Expr::Lit(_) => true,
+            Expr::Binary(a, op, b) => {
+                *op != BinOp::Sub && a.preserves_remainders() && b.preserves_remainders()
+            }
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        let tokens = tokenize(s)?;
+        let mut pos = 0;
+        let expr = Self::parse_sum(&tokens, &mut pos)?;
+        match tokens.get(pos) {
+            None => Ok(expr),
+            Some(t) => Err(anyhow!("unexpected {:?} in {:?}", t, s)),
+        }
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn parse_sum(tokens: &[Token], pos: &mut usize) -> Result<Self> {
+        let mut expr = Self::parse_product(tokens, pos)?;
+        while let Some(Token::Op(op @ (BinOp::Add | BinOp::Sub))) = tokens.get(*pos) {
+            *pos += 1;
+            let rhs = Self::parse_product(tokens, pos)?;
+            expr = Expr::Binary(Box::new(expr), *op, Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    /// product := atom ('*' atom)*
+    fn parse_product(tokens: &[Token], pos: &mut usize) -> Result<Self> {
+        let mut expr = Self::parse_atom(tokens, pos)?;
+        while let Some(Token::Op(BinOp::Mul)) = tokens.get(*pos) {
+            *pos += 1;
+            let rhs = Self::parse_atom(tokens, pos)?;
+            expr = Expr::Binary(Box::new(expr), BinOp::Mul, Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    /// atom := 'old' | literal | '(' sum ')'
+    fn parse_atom(tokens: &[Token], pos: &mut usize) -> Result<Self> {
+        let token = tokens.get(*pos).context("unexpected end of expression")?;
+        *pos += 1;
+        match token {
+            Token::Old => Ok(Expr::Old),
+            Token::Lit(v) => Ok(Expr::Lit(*v)),
+            Token::Open => {
+                let expr = Self::parse_sum(tokens, pos)?;
+                match tokens.get(*pos) {
+                    Some(Token::Close) => {
+                        *pos += 1;
+                        Ok(expr)
+                    }
+                    _ => Err(anyhow!("missing closing parenthesis")),
+                }
+            }
+            t => Err(anyhow!("expected a value, found {:?}", t)),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Lit(v) => write!(f, "{}", v),
+            // parenthesize sums inside products, everything else reads the same without.
+            Expr::Binary(a, BinOp::Mul, b) => {
+                for (i, e) in [a, b].into_iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " * ")?;
+                    }
+                    match **e {
+                        Expr::Binary(_, BinOp::Add | BinOp::Sub, _) => write!(f, "({})", e)?,
+                        _ => write!(f, "{}", e)?,
+                    }
+                }
+                Ok(())
+            }
+            Expr::Binary(a, op, b) => match **b {
+                Expr::Binary(_, BinOp::Add | BinOp::Sub, _) => write!(f, "{} {} ({})", a, op, b),
+                _ => write!(f, "{} {} {}", a, op, b),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Old,
+    Lit(u64),
+    Op(BinOp),
+    Open,
+    Close,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>> {
+    let mut tokens = vec![];
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let (token, len) = match c {
+            '+' => (Token::Op(BinOp::Add), 1),
+            '-' => (Token::Op(BinOp::Sub), 1),
+            '*' => (Token::Op(BinOp::Mul), 1),
+            '(' => (Token::Open, 1),
+            ')' => (Token::Close, 1),
+            _ if rest.starts_with("old") => (Token::Old, 3),
+            '0'..='9' => {
+                let len = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+                (Token::Lit(rest[..len].parse()?), len)
+            }
+            _ => return Err(anyhow!("unexpected {:?} in {:?}", c, s)),
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+#[derive(Debug)]
+struct Test {
+    divisor: u64,
+    if_true: usize,
+    if_false: usize,
+}
+
+impl Test {
+    fn target(&self, worry: u64) -> usize {
+        if worry.is_multiple_of(self.divisor) {
+            self.if_true
+        } else {
+            self.if_false
+        }
+    }
+}
+
+#[derive(Debug)]
 struct Monkey {
     items: VecDeque<u64>,
-    op: OpFunc,
-    test: TestFunc,
-    divisor: u64,
+    op: Expr,
+    test: Test,
 }
 
 impl Monkey {
@@ -62,66 +344,58 @@ impl Monkey {
         self.items.is_empty()
     }
 
-    fn throw(&mut self, divisor: u64) -> Result<(u64, usize)> {
+    /// Inspects the next item and returns its old and new worry level and the target monkey.
+    fn throw(&mut self, divisor: u64) -> Result<(u64, u64, usize)> {
         let item = self.items.pop_front().context("nothing to throw")?;
-        let new_worry = (self.op)(item) / divisor;
-        let to = (self.test)(new_worry);
-        Ok((new_worry, to))
+        let new_worry = self
+            .op
+            .eval(item)
+            .with_context(|| format!("new = {} overflows for old = {}", self.op, item))?
+            / divisor;
+        let to = self.test.target(new_worry);
+        Ok((item, new_worry, to))
     }
 
     fn create_items(s: &str) -> Result<VecDeque<u64>> {
         s.strip_prefix("  Starting items: ")
             .context("no items")?
             .split(", ")
+            .filter(|s| !s.is_empty())
             .map(|s| s.parse().context("can't parse item"))
             .collect()
     }
 
-    fn create_op(s: &str) -> Result<OpFunc> {
-        let parts: Vec<&str> = s
-            .strip_prefix("  Operation: new = ")
-            .context("no operation")?
-            .split(' ')
-            .collect();
-        match parts[1] {
-            "+" => {
-                let operand: u64 = parts[2].parse()?;
-                Ok(Box::new(move |x| x + operand))
-            }
-            "*" => match parts[2] {
-                "old" => Ok(Box::new(|x| x * x)),
-                _ => {
-                    let operand: u64 = parts[2].parse()?;
-                    Ok(Box::new(move |x| x * operand))
-                }
-            },
-            _ => Err(anyhow!("Unknown operation")),
-        }
+    fn create_op(s: &str) -> Result<Expr> {
+        Expr::parse(
+            s.strip_prefix("  Operation: new = ")
+                .context("no operation")?,
+        )
     }
 
-    fn create_test(s: Vec<&str>) -> Result<(TestFunc, u64)> {
+    fn create_test(s: Vec<&str>) -> Result<Test> {
+        if s.len() != 3 {
+            return Err(anyhow!("expected a test and two targets"));
+        }
         let divisor: u64 = s[0]
             .strip_prefix("  Test: divisible by ")
             .context("no test")?
             .parse()?;
-        let true_monkey = s[1]
+        if divisor == 0 {
+            return Err(anyhow!("can't test divisibility by 0"));
+        }
+        let if_true = s[1]
             .strip_prefix("    If true: throw to monkey ")
             .context("no true monkey")?
             .parse()?;
-        let false_monkey = s[2]
+        let if_false = s[2]
             .strip_prefix("    If false: throw to monkey ")
             .context("no false monkey")?
             .parse()?;
-        Ok((
-            Box::new(move |x| {
-                if x % divisor == 0 {
-                    true_monkey
-                } else {
-                    false_monkey
-                }
-            }),
+        Ok(Test {
             divisor,
-        ))
+            if_true,
+            if_false,
+        })
     }
 }
 
@@ -134,17 +408,60 @@ impl FromStr for Monkey {
         let items = Monkey::create_items(lines.next().context("no items")?)?;
         let op = Monkey::create_op(lines.next().context("no operation")?)?;
         let test = Monkey::create_test(lines.collect())?;
-        Ok(Self {
-            items,
-            op,
-            test: test.0,
-            divisor: test.1,
-        })
+        Ok(Self { items, op, test })
+    }
+}
+
+/// Runs the rounds of a part and reports on them.
+fn report(input: Input, part: u8, kind: &str) -> Result<()> {
+    let (num_rounds, worry_divisor) = match part {
+        1 => (20, 3),
+        2 => (10000, 1),
+        _ => return Err(anyhow!("unknown part {}", part)),
+    };
+    let mut monkeys = parse_monkeys(input)?;
+    match kind {
+        "rounds" => {
+            let mut dump = RoundDump {
+                out: std::io::stdout().lock(),
+            };
+            run_rounds(&mut monkeys, num_rounds, worry_divisor, &mut dump)?;
+        }
+        "dot" => {
+            let mut graph = ThrowGraph::default();
+            run_rounds(&mut monkeys, num_rounds, worry_divisor, &mut graph)?;
+            print!("{}", graph.to_dot());
+        }
+        _ => return Err(anyhow!("unknown report {:?}, expected rounds or dot", kind)),
     }
+    Ok(())
+}
+
+struct Args {
+    report: Option<String>,
+    part: u8,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        report: args.opt_value_from_str("--report")?,
+        part: args.opt_value_from_str("--part")?.unwrap_or(1),
+    })
 }
 
 fn main() {
     let input = &advent_of_code::read_file("inputs", 11);
+
+    // `--report rounds|dot [--part 1|2]` prints the state after every round or the throw graph.
+    let args = parse_args().expect("invalid arguments");
+    if let Some(kind) = args.report {
+        if let Err(e) = report(Input::new(input), args.part, &kind) {
+            eprintln!("{:#}", e);
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -164,4 +481,93 @@ mod tests {
         let input = advent_of_code::read_file("examples", 11);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 2713310158);
     }
+
+    #[test]
+    fn test_expr() {
+        let e = |s: &str| Expr::parse(s).unwrap();
+        assert_eq!(e("old + old").eval(3), Some(6));
+        assert_eq!(e("2 * old").eval(3), Some(6));
+        assert_eq!(e("old * old - 1 + 2 * 3").eval(4), Some(21));
+        assert_eq!(e("(old + 1) * 2").eval(4), Some(10));
+        assert_eq!(e("old - 5").eval(4), None);
+        assert_eq!(e("old * old").eval(u64::MAX), None);
+        assert_eq!(
+            e("(old + 1) * (2 - old)").to_string(),
+            "(old + 1) * (2 - old)"
+        );
+        assert_eq!(e("old-(1+old)").to_string(), "old - (1 + old)");
+        assert!(Expr::parse("old /").is_err());
+        assert!(Expr::parse("old + ").is_err());
+        assert!(Expr::parse("(old").is_err());
+    }
+
+    #[test]
+    fn test_common_divisor() {
+        let input = advent_of_code::read_file("examples", 11);
+        let mut monkeys = parse_monkeys(Input::new(&input)).unwrap();
+        assert_eq!(common_divisor(&monkeys, 1), Some(96577));
+        assert_eq!(common_divisor(&monkeys, 3), None);
+
+        monkeys[0].op = Expr::parse("old - 1").unwrap();
+        assert_eq!(common_divisor(&monkeys, 1), None);
+    }
+
+    #[test]
+    fn test_observers() {
+        let input = advent_of_code::read_file("examples", 11);
+        let mut monkeys = parse_monkeys(Input::new(&input)).unwrap();
+        let mut dump = RoundDump { out: vec![] };
+        run_rounds(&mut monkeys, 1, 3, &mut dump).unwrap();
+        let dump = String::from_utf8(dump.out).unwrap();
+        assert!(dump.starts_with(
+            "After round 1, the monkeys are holding items with these worry levels:\n\
+             Monkey 0: 20, 23, 27, 26\n\
+             Monkey 1: 2080, 25, 167, 207, 401, 1046\n\
+             Monkey 2: \n\
+             Monkey 3: \n\n\
+             Monkey 0 inspected items 2 times.\n"
+        ));
+
+        // a full writer fails the run instead of panicking.
+        let mut monkeys = parse_monkeys(Input::new(&input)).unwrap();
+        let mut buf = [0; 16];
+        let mut dump = RoundDump { out: &mut buf[..] };
+        assert!(run_rounds(&mut monkeys, 1, 3, &mut dump).is_err());
+
+        let mut monkeys = parse_monkeys(Input::new(&input)).unwrap();
+        let mut graph = ThrowGraph::default();
+        run_rounds(&mut monkeys, 1, 3, &mut graph).unwrap();
+        assert_eq!(graph.edges[&(0, 3)], 2);
+        assert!(graph.to_dot().contains("    0 -> 3 [label=\"2\"];\n"));
+
+        struct Throws(Vec<Throw>);
+        impl Observer for Throws {
+            fn on_throw(&mut self, throw: &Throw) {
+                self.0.push(throw.clone());
+            }
+        }
+        let mut monkeys = parse_monkeys(Input::new(&input)).unwrap();
+        let mut throws = Throws(vec![]);
+        run_rounds(&mut monkeys, 1, 3, &mut throws).unwrap();
+        let first = Throw {
+            round: 1,
+            from: 0,
+            to: 3,
+            old: 79,
+            new: 500,
+        };
+        assert_eq!(throws.0[0], first);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let input = "Monkey 0:\n  Starting items: 4294967296\n  Operation: new = old * old\n  \
+                     Test: divisible by 2\n    If true: throw to monkey 0\n    If false: throw to monkey 0\n";
+        let mut monkeys = parse_monkeys(Input::new(input)).unwrap();
+        let err = run_rounds(&mut monkeys, 1, 1, &mut ()).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "round 1, monkey 0: new = old * old overflows for old = 4294967296"
+        );
+    }
 }
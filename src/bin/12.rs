@@ -1,99 +1,197 @@
 use std::collections::VecDeque;
 
 use advent_of_code::helpers::Input;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 
 pub fn part_one(input: Input) -> Result<u32> {
-    let pr = parse_grid(input)?;
-    bfs(&pr.grid, pr.start, pr.end)
+    let map = parse_grid(input)?;
+    let descent = Descent::from_end(&map);
+    descent
+        .distance(map.start)
+        .context("E can't be reached from S")
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
-    let pr = parse_grid(input)?;
-    let mut starts = pr.lowests.clone();
-    starts.push(pr.start);
-    starts
-        .iter()
-        .map(|&s| bfs(&pr.grid, s, pr.end).unwrap_or(u32::MAX))
-        .min()
-        .ok_or_else(|| anyhow!("no paths found"))
+    let map = parse_grid(input)?;
+    let descent = Descent::from_end(&map);
+    let start = descent
+        .closest_lowest(&map)
+        .context("E can't be reached from any square of elevation a")?;
+    descent.distance(start).context("no distance to start")
 }
 
-fn bfs(grid: &Vec<Vec<u8>>, start: (usize, usize), end: (usize, usize)) -> Result<u32> {
-    let mut visited = vec![vec![false; grid[0].len()]; grid.len()];
-    visited[start.0][start.1] = true;
-    let mut q = VecDeque::from([(start, 0)]);
-    let dirs = [(0, 1), (0, -1), (1, 0), (-1, 0)];
+struct HeightMap {
+    rows: usize,
+    cols: usize,
+    /// elevations from 0 (`a`) to 25 (`z`), row-major.
+    heights: Vec<u8>,
+    start: usize,
+    end: usize,
+}
 
-    while !q.is_empty() {
-        let ((i, j), dist) = q.pop_front().unwrap();
-        if (i, j) == end {
-            return Ok(dist);
-        }
-        let h = grid[i][j];
-        for dir in dirs {
-            let (m, n) = (i as i32 + dir.0, j as i32 + dir.1);
-            if m < 0 || n < 0 || m >= grid.len() as i32 || n >= grid[0].len() as i32 {
-                continue;
-            }
-            let (m, n) = (m as usize, n as usize);
-            if visited[m][n] || grid[m][n] as i32 - h as i32 > 1 {
-                continue;
+impl HeightMap {
+    /// Squares next to `pos`, in the order up, down, left, right.
+    fn neighbors(&self, pos: usize) -> impl Iterator<Item = usize> {
+        let (r, c, cols) = (pos / self.cols, pos % self.cols, self.cols);
+        [
+            (r > 0).then(|| pos - cols),
+            (r + 1 < self.rows).then(|| pos + cols),
+            (c > 0).then(|| pos - 1),
+            (c + 1 < cols).then(|| pos + 1),
+        ]
+        .into_iter()
+        .flatten()
+    }
+}
+
+/// Every square's shortest distance to `E`, found by a single BFS from `E` that steps down at
+/// most one elevation at a time, i.e. along the reversed climbing moves.
+struct Descent {
+    dist: Vec<Option<u32>>,
+    /// the next square on a shortest route to `E`.
+    next: Vec<Option<usize>>,
+}
+
+impl Descent {
+    fn from_end(map: &HeightMap) -> Self {
+        let mut dist = vec![None; map.heights.len()];
+        let mut next = vec![None; map.heights.len()];
+        dist[map.end] = Some(0);
+        let mut q = VecDeque::from([map.end]);
+
+        while let Some(pos) = q.pop_front() {
+            let d = dist[pos].unwrap();
+            for n in map.neighbors(pos) {
+                if dist[n].is_some() || map.heights[n] + 1 < map.heights[pos] {
+                    continue;
+                }
+                dist[n] = Some(d + 1);
+                next[n] = Some(pos);
+                q.push_back(n);
             }
-            visited[m][n] = true;
-            q.push_back(((m, n), dist + 1));
         }
+
+        Self { dist, next }
     }
 
-    Err(anyhow!("Path not found!"))
+    fn distance(&self, pos: usize) -> Option<u32> {
+        self.dist[pos]
+    }
+
+    /// The square of elevation `a` closest to `E`.
+    fn closest_lowest(&self, map: &HeightMap) -> Option<usize> {
+        (0..map.heights.len())
+            .filter(|&p| map.heights[p] == 0)
+            .filter(|&p| self.dist[p].is_some())
+            .min_by_key(|&p| self.dist[p])
+    }
+
+    /// The squares of a shortest route from `start` to `E`, both included.
+    fn route(&self, start: usize) -> Option<Vec<usize>> {
+        self.dist[start]?;
+        let mut route = vec![start];
+        let mut pos = start;
+        while let Some(n) = self.next[pos] {
+            route.push(n);
+            pos = n;
+        }
+        Some(route)
+    }
 }
 
-struct ParseResult {
-    grid: Vec<Vec<u8>>,
-    start: (usize, usize),
-    lowests: Vec<(usize, usize)>,
-    end: (usize, usize),
+/// Draws a route like the puzzle description does: every square on it has an arrow pointing
+/// to the next one, `E` marks the end and everything else is `.`.
+fn render_route(map: &HeightMap, route: &[usize]) -> String {
+    let mut grid = vec![vec!['.'; map.cols]; map.rows];
+    for w in route.windows(2) {
+        let (from, to) = (w[0], w[1]);
+        let arrow = match to as isize - from as isize {
+            1 => '>',
+            -1 => '<',
+            d if d > 0 => 'v',
+            _ => '^',
+        };
+        grid[from / map.cols][from % map.cols] = arrow;
+    }
+    grid[map.end / map.cols][map.end % map.cols] = 'E';
+    grid.iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect()
 }
 
-fn parse_grid(input: Input) -> Result<ParseResult> {
-    let mut start = (usize::MAX, usize::MAX);
-    let mut lowests = vec![];
-    let mut end = (usize::MAX, usize::MAX);
-    let grid = input
-        .as_str()
-        .lines()
-        .enumerate()
-        .map(|(i, l)| {
-            l.chars()
-                .enumerate()
-                .map(|(j, c)| match c {
-                    'S' => {
-                        start = (i, j);
-                        0
-                    }
-                    'E' => {
-                        end = (i, j);
-                        25
-                    }
-                    'a' => {
-                        lowests.push((i, j));
-                        0
-                    }
-                    _ => c as u8 - b'a',
-                })
-                .collect()
-        })
-        .collect();
-    Ok(ParseResult {
-        grid,
-        start,
-        lowests,
-        end,
+fn parse_grid(input: Input) -> Result<HeightMap> {
+    let mut start = None;
+    let mut end = None;
+    let mut heights = vec![];
+    let mut cols = None;
+    let mut rows = 0;
+    for (i, l) in input.as_str().lines().enumerate() {
+        let len = l.chars().count();
+        if *cols.get_or_insert(len) != len {
+            return Err(anyhow!(
+                "row {} has {} squares, expected {}",
+                i + 1,
+                len,
+                cols.unwrap_or_default()
+            ));
+        }
+        for c in l.chars() {
+            let h = match c {
+                'S' => {
+                    start = Some(heights.len());
+                    b'a'
+                }
+                'E' => {
+                    end = Some(heights.len());
+                    b'z'
+                }
+                'a'..='z' => c as u8,
+                _ => return Err(anyhow!("row {}: invalid elevation {:?}", i + 1, c)),
+            };
+            heights.push(h - b'a');
+        }
+        rows += 1;
+    }
+    Ok(HeightMap {
+        rows,
+        cols: cols.unwrap_or_default(),
+        heights,
+        start: start.context("no start square S")?,
+        end: end.context("no end square E")?,
     })
 }
 
+/// Renders the shortest route of a part over the height map.
+fn show_route(input: Input, part: u8) -> Result<String> {
+    let map = parse_grid(input)?;
+    let descent = Descent::from_end(&map);
+    let start = match part {
+        1 => map.start,
+        2 => descent
+            .closest_lowest(&map)
+            .context("E can't be reached from any square of elevation a")?,
+        _ => return Err(anyhow!("unknown part {}", part)),
+    };
+    let route = descent.route(start).context("E can't be reached")?;
+    Ok(render_route(&map, &route))
+}
+
 fn main() {
     let input = &advent_of_code::read_file("inputs", 12);
+
+    // `--route 1|2` draws the shortest route of a part.
+    let mut args = pico_args::Arguments::from_env();
+    let part: Option<u8> = args
+        .opt_value_from_str("--route")
+        .expect("invalid arguments");
+    if let Some(part) = part {
+        match show_route(Input::new(input), part) {
+            Ok(r) => print!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -113,4 +211,37 @@ mod tests {
         let input = advent_of_code::read_file("examples", 12);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 29);
     }
+
+    #[test]
+    fn test_generated() {
+        let input = advent_of_code::generators::generate(12, 1).unwrap();
+        let one = part_one(Input::new(&input)).unwrap();
+        let two = part_two(Input::new(&input)).unwrap();
+        assert!(two <= one);
+    }
+
+    #[test]
+    fn test_route() {
+        let input = advent_of_code::read_file("examples", 12);
+        let map = parse_grid(Input::new(&input)).unwrap();
+        let descent = Descent::from_end(&map);
+        let route = descent.route(map.start).unwrap();
+        assert_eq!(route.len(), 32);
+        assert!(route
+            .windows(2)
+            .all(|w| map.heights[w[1]] <= map.heights[w[0]] + 1));
+
+        assert_eq!(
+            render_route(&map, &route),
+            ">>vv<<<<\n..vvv<<^\n..vv>E^^\n..v>>>^^\n..>>>>>^\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_maps() {
+        assert!(parse_grid(Input::new("Sbc\nabc\n")).is_err());
+        assert!(parse_grid(Input::new("abc\nabE\n")).is_err());
+        assert!(parse_grid(Input::new("Sbc\nab\nE\n")).is_err());
+        assert!(part_one(Input::new("Sbz\nabE\n")).is_err());
+    }
 }
@@ -1,65 +1,184 @@
 use std::cmp::Ordering;
+use std::fmt;
+use std::slice;
+use std::str::FromStr;
 
 use advent_of_code::helpers::Input;
 use anyhow::{anyhow, Context, Result};
 use itertools::Itertools;
-use serde_json::{json, Value};
 
 pub fn part_one(input: Input) -> Result<usize> {
-    Ok(input
+    let pairs = input
         .as_str()
         .split("\n\n")
-        .map(|p| {
+        .enumerate()
+        .map(|(n, p)| {
             p.lines()
-                .map(|l| serde_json::from_str::<Value>(l).unwrap())
+                .map(str::parse::<Packet>)
                 .collect_tuple()
-                .unwrap()
+                .context("expected two packets")
+                .and_then(|(a, b)| Ok((a?, b?)))
+                .with_context(|| format!("pair {}", n + 1))
         })
-        .positions(|(a, b)| ord(&a, &b).unwrap() == Ordering::Less)
-        .map(|i| i + 1)
-        .sum())
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(pairs.iter().positions(|(a, b)| a < b).map(|i| i + 1).sum())
 }
 
 pub fn part_two(input: Input) -> Result<usize> {
-    let mut packets: Vec<Value> = input
+    let packets = parse_packets(input)?;
+    let dividers = ["[[2]]".parse()?, "[[6]]".parse()?];
+    Ok(decoder_key(&packets, &dividers))
+}
+
+fn parse_packets(input: Input) -> Result<Vec<Packet>> {
+    input
         .as_str()
-        .split_ascii_whitespace()
-        .map(|l| serde_json::from_str::<Value>(l).context("bad json conversion"))
-        .collect::<Result<_>>()?;
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.is_empty())
+        .map(|(n, l)| l.parse().with_context(|| format!("line {}", n + 1)))
+        .collect()
+}
 
-    let beacons = vec![json!([json!([2])]), json!([json!([6])])];
+/// The product of the 1-based indices the `dividers` would end up at if they were sorted
+/// together with `packets`. Counts the packets sorting before each divider instead of sorting.
+fn decoder_key(packets: &[Packet], dividers: &[Packet]) -> usize {
+    dividers
+        .iter()
+        .enumerate()
+        .map(|(i, d)| {
+            let before = packets.iter().filter(|p| *p < d).count();
+            // ties between dividers keep their order.
+            let dividers_before = dividers
+                .iter()
+                .enumerate()
+                .filter(|&(j, e)| e < d || (e == d && j < i))
+                .count();
+            before + dividers_before + 1
+        })
+        .product()
+}
 
-    packets.extend(beacons.iter().cloned());
-    packets.sort_by(|a, b| ord(a, b).unwrap());
+#[derive(Debug, Clone)]
+enum Packet {
+    Int(u64),
+    List(Vec<Packet>),
+}
 
-    Ok(packets
-        .iter()
-        .positions(|p| beacons.contains(p))
-        .map(|i| i + 1)
-        .product())
+impl Ord for Packet {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Packet::Int(a), Packet::Int(b)) => a.cmp(b),
+            (Packet::List(a), Packet::List(b)) => a.iter().cmp(b),
+            // an integer compares like a list holding only that integer.
+            (Packet::Int(_), Packet::List(b)) => slice::from_ref(self).iter().cmp(b),
+            (Packet::List(a), Packet::Int(_)) => a.iter().cmp(slice::from_ref(other)),
+        }
+    }
 }
 
-fn num_ord(a: &serde_json::Number, b: &serde_json::Number) -> Result<Ordering> {
-    Ok(a.as_u64()
-        .context("bad number conversion")?
-        .cmp(&b.as_u64().context("bad number conversion")?))
+impl PartialOrd for Packet {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Packets are equal if they are in the right order both ways, so `2`, `[2]` and `[[2]]` all
+/// are.
+impl PartialEq for Packet {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
 }
 
-fn ord(x: &Value, y: &Value) -> Result<Ordering> {
-    match (x, y) {
-        (Value::Number(a), Value::Number(b)) => num_ord(a, b),
-        (Value::Array(a), Value::Array(b)) => {
-            for (a_i, b_i) in a.iter().zip(b) {
-                let ord = ord(a_i, b_i)?;
-                if ord != Ordering::Equal {
-                    return Ok(ord);
+impl Eq for Packet {}
+
+impl fmt::Display for Packet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Packet::Int(v) => write!(f, "{}", v),
+            Packet::List(items) => write!(f, "[{}]", items.iter().join(",")),
+        }
+    }
+}
+
+impl FromStr for Packet {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            bytes: s.as_bytes(),
+            pos: 0,
+        };
+        let packet = parser.packet()?;
+        match parser.peek() {
+            None => Ok(packet),
+            Some(_) => Err(parser.error("end of packet")),
+        }
+    }
+}
+
+/// Reads a packet straight from the bytes of a line, without tokenizing it first.
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn error(&self, expected: &str) -> anyhow::Error {
+        match self.peek() {
+            Some(b) => anyhow!(
+                "expected {} at column {}, found {:?}",
+                expected,
+                self.pos + 1,
+                b as char
+            ),
+            None => anyhow!("expected {}, found end of line", expected),
+        }
+    }
+
+    fn packet(&mut self) -> Result<Packet> {
+        match self.peek() {
+            Some(b'[') => self.list(),
+            Some(b'0'..=b'9') => self.int(),
+            _ => Err(self.error("'[' or a digit")),
+        }
+    }
+
+    fn list(&mut self) -> Result<Packet> {
+        self.pos += 1;
+        let mut items = vec![];
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Packet::List(items));
+        }
+        loop {
+            items.push(self.packet()?);
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Packet::List(items));
                 }
+                _ => return Err(self.error("',' or ']'")),
             }
-            Ok(a.len().cmp(&b.len()))
         }
-        (Value::Number(_), Value::Array(_)) => ord(&json!([x.clone()]), y),
-        (Value::Array(_), Value::Number(_)) => ord(x, &json!([y.clone()])),
-        _ => Err(anyhow!("Unexpected value")),
+    }
+
+    fn int(&mut self) -> Result<Packet> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let digits = std::str::from_utf8(&self.bytes[start..self.pos])?;
+        Ok(Packet::Int(digits.parse().with_context(|| {
+            format!("integer at column {} is too large", start + 1)
+        })?))
     }
 }
 
@@ -72,6 +191,14 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    fn packet() -> impl Strategy<Value = Packet> {
+        let leaf = (0_u64..11).prop_map(Packet::Int);
+        leaf.prop_recursive(4, 32, 5, |inner| {
+            prop::collection::vec(inner, 0..5).prop_map(Packet::List)
+        })
+    }
 
     #[test]
     fn test_part_one() {
@@ -84,4 +211,66 @@ mod tests {
         let input = advent_of_code::read_file("examples", 13);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 140);
     }
+
+    #[test]
+    fn test_parse() {
+        let p: Packet = "[1,[2,[3,[4,[5,6,7]]]],8,9]".parse().unwrap();
+        assert_eq!(p.to_string(), "[1,[2,[3,[4,[5,6,7]]]],8,9]");
+        assert_eq!(
+            format!("{:#}", "[1,,2]".parse::<Packet>().unwrap_err()),
+            "expected '[' or a digit at column 4, found ','"
+        );
+        assert!("[1,2".parse::<Packet>().is_err());
+        assert!("[1]]".parse::<Packet>().is_err());
+        assert!("[99999999999999999999]".parse::<Packet>().is_err());
+
+        let input = advent_of_code::read_file("examples", 13);
+        let packets = parse_packets(Input::new(&input)).unwrap();
+        let dividers = ["[[1]]".parse().unwrap(), "[[10]]".parse().unwrap()];
+        assert_eq!(decoder_key(&packets, &dividers), 4 * 18);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_display_round_trips(a in packet()) {
+            let s = a.to_string();
+            let parsed: Packet = s.parse().unwrap();
+            prop_assert_eq!(parsed.to_string(), s);
+        }
+
+        #[test]
+        fn prop_ord_reflexive(a in packet()) {
+            prop_assert_eq!(a.cmp(&a), Ordering::Equal);
+        }
+
+        #[test]
+        fn prop_ord_antisymmetric(a in packet(), b in packet()) {
+            prop_assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+        }
+
+        #[test]
+        fn prop_ord_transitive(a in packet(), b in packet(), c in packet()) {
+            let mut v = [a, b, c];
+            v.sort();
+            prop_assert_ne!(v[0].cmp(&v[1]), Ordering::Greater);
+            prop_assert_ne!(v[1].cmp(&v[2]), Ordering::Greater);
+            prop_assert_ne!(v[0].cmp(&v[2]), Ordering::Greater);
+        }
+
+        #[test]
+        fn prop_decoder_key_matches_sort(packets in prop::collection::vec(packet(), 0..20)) {
+            let dividers: [Packet; 2] = ["[[2]]".parse().unwrap(), "[[6]]".parse().unwrap()];
+            let mut sorted = packets.clone();
+            sorted.extend(dividers.iter().cloned());
+            sorted.sort();
+            // a stable sort keeps dividers after equal packets, unlike counting, so only compare
+            // when no packet ties with a divider.
+            prop_assume!(packets.iter().all(|p| dividers.iter().all(|d| p != d)));
+            let expected: usize = dividers
+                .iter()
+                .map(|d| sorted.iter().position(|p| p == d).unwrap() + 1)
+                .product();
+            prop_assert_eq!(decoder_key(&packets, &dividers), expected);
+        }
+    }
 }
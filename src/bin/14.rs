@@ -1,141 +1,275 @@
 use advent_of_code::helpers::Input;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use itertools::Itertools;
 
-pub fn part_one(_input: Input) -> Result<u32> {
-    let pr = parse_paths(_input)?;
-    let mut grid = vec![vec![0_u32; pr.y_max + 1]; pr.x_max + 1];
-    fill_grid(&mut grid, &pr.paths);
+/// `x` can go below 0 once sand spreads past the left of the source, `y` is the depth.
+type Point = (i64, usize);
 
-    let mut i = 0;
-    loop {
-        let mut grain = (500, 0);
-        loop {
-            if grain.1 == pr.y_max {
-                break;
-            } else if grid[grain.0][grain.1 + 1] == 0 {
-                grain.1 += 1;
-            } else if grid[grain.0 - 1][grain.1 + 1] == 0 {
-                grain.0 -= 1;
-                grain.1 += 1;
-            } else if grid[grain.0 + 1][grain.1 + 1] == 0 {
-                grain.0 += 1;
-                grain.1 += 1;
-            } else {
-                grid[grain.0][grain.1] = 1;
-                break;
-            }
-        }
+const SOURCE: Point = (500, 0);
+const FLOOR_OFFSET: usize = 2;
 
-        if grain.1 == pr.y_max {
-            break;
-        }
+pub fn part_one(input: Input) -> Result<u32> {
+    let mut sim = SandSim::new(Cave::new(&parse_paths(input)?, SOURCE, None)?);
+    sim.run();
+    Ok(sim.rested)
+}
 
-        i += 1;
-    }
+pub fn part_two(input: Input) -> Result<u32> {
+    let cave = Cave::new(&parse_paths(input)?, SOURCE, Some(FLOOR_OFFSET))?;
+    let mut sim = SandSim::new(cave);
+    sim.run();
+    Ok(sim.rested)
+}
 
-    Ok(i)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Air,
+    Rock,
+    Sand,
 }
 
-pub fn part_two(_input: Input) -> Result<u32> {
-    let pr = parse_paths(_input)?;
-    let mut grid = vec![vec![0_u32; pr.y_max + 3]; pr.x_max + 5000];
-    fill_grid(&mut grid, &pr.paths);
-    grid.iter_mut().for_each(|row| {
-        row[pr.y_max + 2] = 1;
-    });
-
-    let mut i = 0;
-    loop {
-        let mut grain = (500, 0);
-        loop {
-            if grid[grain.0][grain.1 + 1] == 0 {
-                grain.1 += 1;
-            } else if grid[grain.0 - 1][grain.1 + 1] == 0 {
-                grain.0 -= 1;
-                grain.1 += 1;
-            } else if grid[grain.0 + 1][grain.1 + 1] == 0 {
-                grain.0 += 1;
-                grain.1 += 1;
-
-                let len = grid.len();
-                if len == grain.0 + 1 {
-                    grid.push(vec![0; pr.y_max + 3]);
-                    grid[len - 1][pr.y_max + 2] = 1;
+/// The part of the cave sand can reach. Columns are stored relative to `x_min`.
+struct Cave {
+    x_min: i64,
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    source: Point,
+    /// the depth of the infinite floor, if there is one. Without it, sand falling out of the
+    /// stored area is lost to the abyss.
+    floor: Option<usize>,
+}
+
+impl Cave {
+    /// Builds a cave from rock paths. With a floor `floor_offset` below the lowest rock, it is
+    /// exactly as wide as the pile of sand under the source can get.
+    fn new(paths: &[Vec<Point>], source: Point, floor_offset: Option<usize>) -> Result<Self> {
+        let points = || paths.iter().flatten().chain([&source]);
+        let y_max = points().map(|p| p.1).max().unwrap_or_default();
+        let (mut x_min, mut x_max) = points()
+            .map(|p| p.0)
+            .minmax()
+            .into_option()
+            .unwrap_or_default();
+
+        let floor = floor_offset.map(|offset| y_max + offset);
+        let height = match floor {
+            Some(floor) => {
+                let spread = floor
+                    .checked_sub(source.1 + 1)
+                    .context("no room below the source")?;
+                x_min = x_min.min(source.0 - spread as i64);
+                x_max = x_max.max(source.0 + spread as i64);
+                floor
+            }
+            None => {
+                // sand can still rest next to the outermost rocks.
+                x_min -= 1;
+                x_max += 1;
+                y_max + 1
+            }
+        };
+
+        let width = (x_max - x_min + 1) as usize;
+        let mut cave = Self {
+            x_min,
+            width,
+            height,
+            cells: vec![Cell::Air; width * height],
+            source,
+            floor,
+        };
+        for path in paths {
+            for (&(x1, y1), &(x2, y2)) in path.iter().tuple_windows() {
+                if x1 != x2 && y1 != y2 {
+                    return Err(anyhow!(
+                        "rock path {},{} -> {},{} is not straight",
+                        x1,
+                        y1,
+                        x2,
+                        y2
+                    ));
+                }
+                for x in x1.min(x2)..=x1.max(x2) {
+                    for y in y1.min(y2)..=y1.max(y2) {
+                        if let Some(i) = cave.index(x, y) {
+                            cave.cells[i] = Cell::Rock;
+                        }
+                    }
                 }
-            } else {
-                grid[grain.0][grain.1] = 1;
-                break;
             }
         }
+        Ok(cave)
+    }
 
-        if grain == (500, 0) {
-            break;
-        }
+    fn index(&self, x: i64, y: usize) -> Option<usize> {
+        let col = usize::try_from(x - self.x_min).ok()?;
+        (col < self.width && y < self.height).then_some(y * self.width + col)
+    }
 
-        i += 1;
+    /// `None` outside of the stored area, which is the abyss or the floor.
+    fn get(&self, x: i64, y: usize) -> Option<Cell> {
+        self.index(x, y).map(|i| self.cells[i])
+    }
+
+    /// Draws the cave like the puzzle description does: `#` for rock and the floor, `o` for sand,
+    /// `+` for the source and `.` for air.
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for y in 0..self.height {
+            for x in self.x_min..self.x_min + self.width as i64 {
+                out.push(match self.get(x, y) {
+                    _ if (x, y) == self.source && self.get(x, y) == Some(Cell::Air) => '+',
+                    Some(Cell::Rock) => '#',
+                    Some(Cell::Sand) => 'o',
+                    _ => '.',
+                });
+            }
+            out.push('\n');
+        }
+        if self.floor.is_some() {
+            out += &"#".repeat(self.width);
+            out.push('\n');
+        }
+        out
     }
+}
 
-    Ok(i + 1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Grain {
+    Rested(i64, usize),
+    /// fell out of the cave.
+    Lost,
+    /// the source is covered with sand.
+    Blocked,
 }
 
-struct ParseResult {
-    paths: Vec<Vec<(usize, usize)>>,
-    x_max: usize,
-    y_max: usize,
+/// Drops grains one at a time. The path of the last grain is kept, and the next grain starts
+/// from the last position on it that is still open, since everything above falls the same way.
+struct SandSim {
+    cave: Cave,
+    path: Vec<Point>,
+    rested: u32,
+    /// how the simulation ended, returned for every grain dropped after that.
+    end: Option<Grain>,
 }
 
-fn fill_grid(grid: &mut [Vec<u32>], paths: &Vec<Vec<(usize, usize)>>) {
-    for path in paths {
-        for i in 1..path.len() {
-            let (x1, y1) = path[i - 1];
-            let (x2, y2) = path[i];
-
-            if x2 > x1 {
-                grid[x1..=x2].iter_mut().for_each(|row| row[y1] = 1);
-            } else if x1 > x2 {
-                grid[x2..=x1].iter_mut().for_each(|row| row[y1] = 1);
-            } else if y2 > y1 {
-                grid[x1][y1..=y2].iter_mut().for_each(|cell| *cell = 1);
-            } else if y1 > y2 {
-                grid[x1][y2..=y1].iter_mut().for_each(|cell| *cell = 1);
+impl SandSim {
+    fn new(cave: Cave) -> Self {
+        let path = vec![cave.source];
+        Self {
+            cave,
+            path,
+            rested: 0,
+            end: None,
+        }
+    }
+
+    fn drop_grain(&mut self) -> Grain {
+        if let Some(end) = self.end {
+            return end;
+        }
+        let Some(&(mut x, mut y)) = self.path.last() else {
+            self.end = Some(Grain::Blocked);
+            return Grain::Blocked;
+        };
+
+        loop {
+            let below = [x, x - 1, x + 1]
+                .into_iter()
+                .map(|nx| (nx, y + 1, self.cave.get(nx, y + 1)))
+                .find(|(_, ny, cell)| match cell {
+                    Some(c) => *c == Cell::Air,
+                    // the floor is solid, the abyss is not.
+                    None => self.cave.floor.is_none_or(|f| *ny < f),
+                });
+            match below {
+                Some((nx, ny, Some(_))) => {
+                    (x, y) = (nx, ny);
+                    self.path.push((x, y));
+                }
+                Some((_, _, None)) => {
+                    self.end = Some(Grain::Lost);
+                    return Grain::Lost;
+                }
+                None => {
+                    let i = self.cave.index(x, y).unwrap();
+                    self.cave.cells[i] = Cell::Sand;
+                    self.path.pop();
+                    self.rested += 1;
+                    return Grain::Rested(x, y);
+                }
             }
         }
     }
+
+    /// Drops grains until one is lost or the source is blocked.
+    fn run(&mut self) {
+        while matches!(self.drop_grain(), Grain::Rested(..)) {}
+    }
 }
 
-fn parse_paths(input: Input) -> Result<ParseResult> {
-    let mut x_max = 0;
-    let mut y_max = 0;
-    let paths = input
+fn parse_paths(input: Input) -> Result<Vec<Vec<Point>>> {
+    input
         .as_str()
         .lines()
-        .map(|l| {
+        .enumerate()
+        .map(|(n, l)| {
             l.split(" -> ")
-                .map(|p| {
-                    p.split(',')
-                        .map(|n| n.parse::<usize>().unwrap())
-                        .collect_tuple()
-                        .ok_or_else(|| anyhow!("Invalid path"))
-                        .map(|(x, y)| {
-                            x_max = x_max.max(x);
-                            y_max = y_max.max(y);
-                            (x, y)
-                        })
-                })
+                .map(parse_point)
                 .collect::<Result<_>>()
+                .with_context(|| format!("line {}: {:?}", n + 1, l))
         })
-        .collect::<Result<_>>()?;
+        .collect()
+}
+
+fn parse_point(s: &str) -> Result<Point> {
+    let (x, y) = s.split(',').collect_tuple().context("expected x,y")?;
+    Ok((x.parse()?, y.parse()?))
+}
 
-    Ok(ParseResult {
-        paths,
-        x_max,
-        y_max,
+/// Drops `step` grains and renders the cave.
+fn render_step(input: Input, source: Point, floor: Option<usize>, step: usize) -> Result<String> {
+    let mut sim = SandSim::new(Cave::new(&parse_paths(input)?, source, floor)?);
+    for _ in 0..step {
+        if !matches!(sim.drop_grain(), Grain::Rested(..)) {
+            break;
+        }
+    }
+    Ok(sim.cave.render())
+}
+
+struct Args {
+    step: Option<usize>,
+    floor: Option<usize>,
+    source: Option<String>,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        step: args.opt_value_from_str("--step")?,
+        floor: args.opt_value_from_str("--floor")?,
+        source: args.opt_value_from_str("--source")?,
     })
 }
 
 fn main() {
     let input = &advent_of_code::read_file("inputs", 14);
+
+    // `--step <n> [--floor <offset>] [--source <x,y>]` draws the cave after n grains of sand.
+    let args = parse_args().expect("invalid arguments");
+    if let Some(step) = args.step {
+        let source = args.source.as_deref().map_or(Ok(SOURCE), parse_point);
+        let rendered =
+            source.and_then(|source| render_step(Input::new(input), source, args.floor, step));
+        match rendered {
+            Ok(r) => print!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -155,4 +289,66 @@ mod tests {
         let input = advent_of_code::read_file("examples", 14);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 93);
     }
+
+    #[test]
+    fn test_render() {
+        let input = advent_of_code::read_file("examples", 14);
+        // the example's picture with one more column on each side, where sand can still fall.
+        let expected = [
+            "......+...",
+            "..........",
+            "..........",
+            "..........",
+            "....#...##",
+            "....#...#.",
+            "..###...#.",
+            "......o.#.",
+            "....oooo#.",
+            "#########.",
+        ]
+        .map(|row| format!(".{}.\n", row))
+        .concat();
+        assert_eq!(
+            render_step(Input::new(&input), SOURCE, None, 5).unwrap(),
+            expected
+        );
+
+        let rendered = render_step(Input::new(&input), SOURCE, Some(2), 93).unwrap();
+        let rows = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(rows.len(), 12);
+        assert_eq!(rows[0], "..........o..........");
+        assert_eq!(rows[10], "ooooo.......ooooooooo");
+        assert_eq!(rows[11], "#".repeat(21));
+    }
+
+    #[test]
+    fn test_path_memo() {
+        let input = advent_of_code::read_file("examples", 14);
+        let mut sim = SandSim::new(
+            Cave::new(&parse_paths(Input::new(&input)).unwrap(), SOURCE, None).unwrap(),
+        );
+        assert_eq!(sim.drop_grain(), Grain::Rested(500, 8));
+        assert_eq!(sim.drop_grain(), Grain::Rested(499, 8));
+        // the next grain starts where the last one left the shared path.
+        assert_eq!(sim.path.last(), Some(&(500, 7)));
+        sim.run();
+        assert_eq!(sim.drop_grain(), Grain::Lost);
+        assert_eq!(sim.drop_grain(), Grain::Lost);
+        assert!(Cave::new(&[vec![(1, 1), (2, 2)]], SOURCE, None).is_err());
+    }
+
+    #[test]
+    fn test_source_near_zero() {
+        // sand slides off the rock below the source to the left of x = 0.
+        let cave = Cave::new(&[vec![(0, 1), (0, 1)]], (0, 0), Some(2)).unwrap();
+        let mut sim = SandSim::new(cave);
+        assert_eq!(sim.drop_grain(), Grain::Rested(-1, 2));
+        sim.run();
+        assert_eq!(sim.rested, 1 + 3 + 5 - 1);
+        assert_eq!(sim.drop_grain(), Grain::Blocked);
+
+        let cave = Cave::new(&[vec![(0, 1), (0, 1)]], (0, 0), None).unwrap();
+        let mut sim = SandSim::new(cave);
+        assert_eq!(sim.drop_grain(), Grain::Lost);
+    }
 }
@@ -1,93 +1,193 @@
 use std::collections::HashSet;
 
-use advent_of_code::helpers::Input;
-use anyhow::{anyhow, Context, Result};
+use advent_of_code::helpers::{
+    interval::{Interval, IntervalSet},
+    Input,
+};
+use anyhow::{Context, Result};
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
 
+type Pos = (i64, i64);
+
 pub fn part_one(input: Input) -> Result<u32> {
     part_one_inner(input, 2000000)
 }
 
-fn part_one_inner(input: Input, y_coord: i32) -> Result<u32> {
-    let lines = parse(input)?;
+fn part_one_inner(input: Input, row: i64) -> Result<u32> {
+    let coverage = parse(input)?;
 
-    let beacons = lines
+    // beacons are always covered by their own sensor.
+    let beacons = coverage
+        .sensors
         .iter()
-        .filter(|pr| pr.beacon.1 == y_coord)
-        .map(|pr| pr.beacon.1)
+        .map(|s| s.beacon)
+        .filter(|b| b.1 == row)
         .collect::<HashSet<_>>();
 
-    let ranges = lines
-        .iter()
-        .filter_map(|pr| row_range(&pr.sensor, pr.distance, y_coord))
-        .collect::<Vec<_>>();
-    let merged = merge_ranges(&ranges);
-
-    let marked = merged.iter().fold(0, |sum, (x1, x2)| sum + (x2 - x1 + 1));
-    Ok(marked as u32 - beacons.len() as u32)
+    Ok((coverage.row(row).len() - beacons.len() as i64) as u32)
 }
 
 pub fn part_two(input: Input) -> Result<u64> {
     part_two_inner(input, 4_000_000)
 }
 
-fn part_two_inner(input: Input, max_dim: i32) -> Result<u64> {
-    let lines = parse(input)?;
+fn part_two_inner(input: Input, max_dim: i64) -> Result<u64> {
+    let coverage = parse(input)?;
+    let region = Rect::square(max_dim);
 
-    for i in 0..max_dim {
-        let ranges = lines
-            .iter()
-            .filter_map(|pr| row_range(&pr.sensor, pr.distance, i))
-            .collect::<Vec<_>>();
-        let merged = merge_ranges(&ranges);
-        if merged.len() > 1 {
-            let x = merged[1].0 - 1;
-            let x_c: u64 = x as u64 * 4_000_000;
-            return Ok(x_c + i as u64);
-        }
-    }
+    let (x, y) = coverage
+        .candidates()
+        .filter(|&p| region.contains(p))
+        .find(|&p| !coverage.covers(p))
+        // a cell on the edge of the region doesn't need sensors on all sides to be hidden.
+        .or_else(|| coverage.uncovered_in(&region).first().copied())
+        .context("every position in the search area is covered")?;
+    Ok(x as u64 * 4_000_000 + y as u64)
+}
 
-    Err(anyhow!("no solution found"))
+/// Maps a cell to `(x + y, x - y)`, where the diamonds sensors cover become squares.
+fn rotate((x, y): Pos) -> (i64, i64) {
+    (x + y, x - y)
 }
 
-fn merge_ranges(ranges: &[(i32, i32)]) -> Vec<(i32, i32)> {
-    let mut ranges = ranges.to_vec();
-    ranges.sort_by_key(|(x1, _)| *x1);
-    let mut merged = Vec::new();
-    let mut current = ranges[0];
-    for (x1, x2) in ranges.iter().skip(1) {
-        if *x1 <= current.1 {
-            current.1 = current.1.max(*x2);
-        } else {
-            merged.push(current);
-            current = (*x1, *x2);
-        }
+/// The cell at `(u, v)` in rotated coordinates, `None` if the parities of `u` and `v` differ
+/// and the point lies between cells.
+fn unrotate(u: i64, v: i64) -> Option<Pos> {
+    ((u - v) % 2 == 0).then(|| ((u + v) / 2, (u - v) / 2))
+}
+
+fn manhattan_distance((x1, y1): Pos, (x2, y2): Pos) -> i64 {
+    (x1 - x2).abs() + (y1 - y2).abs()
+}
+
+struct Sensor {
+    pos: Pos,
+    beacon: Pos,
+    /// the distance to the beacon. No other beacon is that close, so every cell within it is
+    /// covered.
+    radius: i64,
+}
+
+impl Sensor {
+    fn covers(&self, p: Pos) -> bool {
+        manhattan_distance(self.pos, p) <= self.radius
+    }
+
+    fn row(&self, row: i64) -> Option<Interval<i64>> {
+        let r = self.radius - (self.pos.1 - row).abs();
+        (r >= 0).then(|| Interval::inclusive(self.pos.0 - r, self.pos.0 + r))
+    }
+
+    /// The `v` values covered at `u` in rotated coordinates.
+    fn column(&self, u: i64) -> Option<Interval<i64>> {
+        let (su, sv) = rotate(self.pos);
+        ((su - u).abs() <= self.radius)
+            .then(|| Interval::inclusive(sv - self.radius, sv + self.radius))
     }
-    merged.push(current);
-    merged
 }
 
-fn row_range((x, y): &(i32, i32), dist: i32, row: i32) -> Option<(i32, i32)> {
-    let r = dist - (y - row).abs();
-    if r < 0 {
-        return None;
+/// An inclusive rectangle of cells.
+struct Rect {
+    x: Interval<i64>,
+    y: Interval<i64>,
+}
+
+impl Rect {
+    /// `0..=max` in both dimensions.
+    fn square(max: i64) -> Self {
+        Self {
+            x: Interval::inclusive(0, max),
+            y: Interval::inclusive(0, max),
+        }
     }
 
-    Some((x - r, x + r))
+    fn contains(&self, (x, y): Pos) -> bool {
+        self.x.contains(x) && self.y.contains(y)
+    }
 }
 
-fn manhattan_distance((x1, y1): &(i32, i32), (x2, y2): &(i32, i32)) -> i32 {
-    (x1 - x2).abs() + (y1 - y2).abs()
+struct Coverage {
+    sensors: Vec<Sensor>,
 }
 
-struct ParsedLine {
-    sensor: (i32, i32),
-    beacon: (i32, i32),
-    distance: i32,
+impl Coverage {
+    fn covers(&self, p: Pos) -> bool {
+        self.sensors.iter().any(|s| s.covers(p))
+    }
+
+    /// The covered cells of a row.
+    fn row(&self, row: i64) -> IntervalSet<i64> {
+        self.sensors.iter().filter_map(|s| s.row(row)).collect()
+    }
+
+    /// Cells where a line just outside one sensor's diamond crosses a line just outside
+    /// another's. A single uncovered cell with covered neighbors on all four sides is one of them.
+    fn candidates(&self) -> impl Iterator<Item = Pos> {
+        let lines = |f: fn(Pos) -> i64| {
+            self.sensors
+                .iter()
+                .flat_map(|s| [f(s.pos) - s.radius - 1, f(s.pos) + s.radius + 1])
+                .sorted()
+                .dedup()
+                .collect_vec()
+        };
+        let us = lines(|(x, y)| x + y);
+        let vs = lines(|(x, y)| x - y);
+        us.into_iter()
+            .cartesian_product(vs)
+            .filter_map(|(u, v)| unrotate(u, v))
+    }
+
+    /// Every uncovered cell of `rect`, in rotated coordinate order.
+    ///
+    /// In rotated coordinates the sensors cover squares, so coverage only changes at their
+    /// edges. Between two edges the covered `v` values are the same for every `u`, and only the
+    /// `u` where a gap in them meets the rectangle are visited.
+    fn uncovered_in(&self, rect: &Rect) -> Vec<Pos> {
+        let (Some(x1), Some(y1)) = (rect.x.last(), rect.y.last()) else {
+            return vec![];
+        };
+        let (x0, y0) = (rect.x.start(), rect.y.start());
+        let us = Interval::inclusive(x0 + y0, x1 + y1);
+        let vs = Interval::inclusive(x0 - y1, x1 - y0);
+
+        let cuts = self
+            .sensors
+            .iter()
+            .flat_map(|s| {
+                let (u, _) = rotate(s.pos);
+                [u - s.radius, u + s.radius + 1]
+            })
+            .filter(|&u| us.contains(u))
+            .chain([us.start(), us.end()])
+            .sorted()
+            .dedup()
+            .collect_vec();
+
+        let mut cells = vec![];
+        for (&a, &b) in cuts.iter().tuple_windows() {
+            let covered: IntervalSet<i64> =
+                self.sensors.iter().filter_map(|s| s.column(a)).collect();
+            for gap in covered.complement_in(vs).iter() {
+                let (g0, g1) = (gap.start(), gap.last().unwrap());
+                // the `u` for which some `v` of the gap lies inside the rectangle.
+                let u_lo = a.max(2 * x0 - g1).max(g0 + 2 * y0);
+                let u_hi = (b - 1).min(g1 + 2 * y1).min(2 * x1 - g0);
+                for u in u_lo..=u_hi {
+                    let v_lo = g0.max(2 * x0 - u).max(u - 2 * y1);
+                    let v_hi = g1.min(2 * x1 - u).min(u - 2 * y0);
+                    let first = v_lo + (u - v_lo).rem_euclid(2);
+                    cells.extend((first..=v_hi).step_by(2).filter_map(|v| unrotate(u, v)));
+                }
+            }
+        }
+        cells
+    }
 }
 
-fn parse_line(l: &str) -> Result<ParsedLine> {
+fn parse_line(l: &str) -> Result<Sensor> {
     lazy_static! {
         static ref RE: Regex = Regex::new(r"Sensor at x=(?P<x1>-?\d+), y=(?P<y1>-?\d+): closest beacon is at x=(?P<x2>-?\d+), y=(?P<y2>-?\d+$)").unwrap();
     }
@@ -96,40 +196,93 @@ fn parse_line(l: &str) -> Result<ParsedLine> {
         .name("x1")
         .context("invalid input")?
         .as_str()
-        .parse::<i32>()?;
+        .parse::<i64>()?;
     let y1 = caps
         .name("y1")
         .context("invalid input")?
         .as_str()
-        .parse::<i32>()?;
+        .parse::<i64>()?;
     let x2 = caps
         .name("x2")
         .context("invalid input")?
         .as_str()
-        .parse::<i32>()?;
+        .parse::<i64>()?;
     let y2 = caps
         .name("y2")
         .context("invalid input")?
         .as_str()
-        .parse::<i32>()?;
+        .parse::<i64>()?;
 
-    Ok(ParsedLine {
-        sensor: (x1, y1),
+    Ok(Sensor {
+        pos: (x1, y1),
         beacon: (x2, y2),
-        distance: manhattan_distance(&(x1, y1), &(x2, y2)),
+        radius: manhattan_distance((x1, y1), (x2, y2)),
     })
 }
 
-fn parse(input: Input) -> Result<Vec<ParsedLine>> {
-    input
+fn parse(input: Input) -> Result<Coverage> {
+    let sensors = input
         .as_str()
         .lines()
-        .map(parse_line)
-        .collect::<Result<Vec<_>>>()
+        .enumerate()
+        .map(|(n, l)| parse_line(l).with_context(|| format!("line {}: {:?}", n + 1, l)))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Coverage { sensors })
+}
+
+/// Answers a coverage query: the covered cells of a row, or the uncovered cells of a rectangle
+/// given as `x0,y0,x1,y1`.
+fn query(input: Input, row: Option<i64>, rect: Option<&str>) -> Result<String> {
+    let coverage = parse(input)?;
+    let mut out = String::new();
+    if let Some(row) = row {
+        out += &format!("{:?}\n", coverage.row(row));
+    }
+    if let Some(rect) = rect {
+        let (x0, y0, x1, y1) = rect
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<Vec<i64>, _>>()?
+            .into_iter()
+            .collect_tuple()
+            .context("expected x0,y0,x1,y1")?;
+        let rect = Rect {
+            x: Interval::inclusive(x0, x1),
+            y: Interval::inclusive(y0, y1),
+        };
+        for (x, y) in coverage.uncovered_in(&rect) {
+            out += &format!("{},{}\n", x, y);
+        }
+    }
+    Ok(out)
+}
+
+struct Args {
+    row: Option<i64>,
+    rect: Option<String>,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        row: args.opt_value_from_str("--row")?,
+        rect: args.opt_value_from_str("--rect")?,
+    })
 }
 
 fn main() {
     let input = &advent_of_code::read_file("inputs", 15);
+
+    // `--row <y>` prints the covered ranges of a row, `--rect <x0,y0,x1,y1>` the uncovered
+    // cells of a rectangle.
+    let args = parse_args().expect("invalid arguments");
+    if args.row.is_some() || args.rect.is_some() {
+        match query(Input::new(input), args.row, args.rect.as_deref()) {
+            Ok(r) => print!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -149,4 +302,65 @@ mod tests {
         let input = advent_of_code::read_file("examples", 15);
         assert_eq!(part_two_inner(Input::new(&input), 20).unwrap(), 56000011);
     }
+
+    #[test]
+    fn test_beacons_on_row() {
+        let input = "Sensor at x=0, y=0: closest beacon is at x=2, y=0\n\
+                     Sensor at x=10, y=0: closest beacon is at x=8, y=0\n";
+        assert_eq!(part_one_inner(Input::new(input), 0).unwrap(), 8);
+        assert_eq!(part_one_inner(Input::new(input), 1).unwrap(), 6);
+    }
+
+    #[test]
+    fn test_uncovered_in() {
+        let input = advent_of_code::read_file("examples", 15);
+        let coverage = parse(Input::new(&input)).unwrap();
+        for rect in [
+            Rect::square(20),
+            Rect {
+                x: Interval::inclusive(-10, 30),
+                y: Interval::inclusive(-5, 25),
+            },
+            Rect {
+                x: Interval::inclusive(14, 14),
+                y: Interval::inclusive(11, 11),
+            },
+        ] {
+            let mut expected = (rect.x.start()..rect.x.end())
+                .cartesian_product(rect.y.start()..rect.y.end())
+                .filter(|&p| !coverage.covers(p))
+                .collect_vec();
+            let mut uncovered = coverage.uncovered_in(&rect);
+            expected.sort();
+            uncovered.sort();
+            assert_eq!(uncovered, expected);
+        }
+
+        assert!(coverage
+            .uncovered_in(&Rect {
+                x: Interval::inclusive(0, 10),
+                y: Interval::inclusive(0, 10),
+            })
+            .is_empty());
+        assert_eq!(
+            coverage
+                .row(11)
+                .complement_in(Interval::inclusive(0, 20))
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_generated() {
+        let input = advent_of_code::generators::generate(15, 1).unwrap();
+        let coverage = parse(Input::new(&input)).unwrap();
+        let uncovered = coverage.uncovered_in(&Rect::square(4_000_000));
+        assert_eq!(uncovered.len(), 1);
+        let (x, y) = uncovered[0];
+        assert_eq!(
+            part_two(Input::new(&input)).unwrap(),
+            x as u64 * 4_000_000 + y as u64
+        );
+    }
 }
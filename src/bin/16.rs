@@ -1,212 +1,269 @@
-use std::{cmp, collections::HashMap};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    fmt,
+};
 
 use advent_of_code::helpers::Input;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
 use lazy_static::lazy_static;
 use regex::Regex;
 
-pub fn part_one(input: Input) -> Result<u32> {
-    let valves = parse(input)?;
-    let v_map = valves
-        .into_iter()
-        .map(|v| (v.name.clone(), v))
-        .collect::<HashMap<_, _>>();
-
-    let on = 0_u64;
-    let mut memo = HashMap::<Memo, u32>::new();
+const START: &str = "AA";
+const MINUTES: u32 = 30;
+/// the minutes it takes to teach a helper how to open valves.
+const TEACHING: u32 = 4;
 
-    Ok(dfs(&v_map, "AA", 30, on, &mut memo))
+pub fn part_one(input: Input) -> Result<u32> {
+    let network = parse(input)?;
+    Ok(network.plan(1, MINUTES).pressure)
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
-    let valves = parse(input)?;
-    let v_map = valves
-        .into_iter()
-        .map(|v| (v.name.clone(), v))
-        .collect::<HashMap<_, _>>();
-
-    let on = 0_u64;
-    let mut memo = HashMap::<Memo2, u32>::new();
-    let distances = calc_distances(&v_map);
-
-    Ok(dfs_2(&mut Dfs2 {
-        valves: &v_map,
-        distances: &distances,
-        valve1: "AA",
-        time1: 26,
-        valve2: "AA",
-        time2: 26,
-        on,
-        memo: &mut memo,
-    }))
-}
-
-fn is_set(n: u64, i: u64) -> bool {
-    n & (1 << i) != 0
-}
-
-fn set_bit(n: u64, i: u64) -> u64 {
-    n | (1 << i)
+    let network = parse(input)?;
+    Ok(network.plan(2, MINUTES - TEACHING).pressure)
 }
 
-#[derive(Eq, Hash, PartialEq)]
-struct Memo {
-    cur: String,
-    time: u32,
-    on: u64,
-}
+/// A set of flowing valves, by their index in the [`Network`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+struct ValveSet(Vec<u64>);
 
-fn dfs(
-    valves: &HashMap<String, Valve>,
-    cur: &str,
-    time: u32,
-    on: u64,
-    memo: &mut HashMap<Memo, u32>,
-) -> u32 {
-    if time == 0 {
-        return 0;
-    }
-    let key = Memo {
-        cur: cur.to_string(),
-        time,
-        on,
-    };
-    if let Some(score) = memo.get(&key) {
-        return *score;
+impl ValveSet {
+    fn contains(&self, i: usize) -> bool {
+        self.0.get(i / 64).is_some_and(|w| w >> (i % 64) & 1 == 1)
     }
 
-    let valve = valves.get(cur).unwrap();
-    let mut max_score = 0_u32;
+    fn with(&self, i: usize) -> Self {
+        let mut words = self.0.clone();
+        if words.len() <= i / 64 {
+            words.resize(i / 64 + 1, 0);
+        }
+        words[i / 64] |= 1 << (i % 64);
+        Self(words)
+    }
 
-    for n in &valve.tunnels {
-        max_score = max_score.max(dfs(valves, n, time - 1, on, memo));
+    fn is_disjoint(&self, other: &Self) -> bool {
+        self.0.iter().zip(&other.0).all(|(a, b)| a & b == 0)
     }
-    if valve.rate > 0 && !is_set(on, valve.index) {
-        let on = set_bit(on, valve.index);
-        max_score = max_score.max(valve.rate * (time - 1) + dfs(valves, cur, time - 1, on, memo));
+
+    fn union(&self, other: &Self) -> Self {
+        let (long, short) = if self.0.len() >= other.0.len() {
+            (self, other)
+        } else {
+            (other, self)
+        };
+        let mut words = long.0.clone();
+        words.iter_mut().zip(&short.0).for_each(|(a, b)| *a |= b);
+        Self(words)
     }
-    memo.insert(key, max_score);
+}
 
-    max_score
+/// The valves worth walking to: the ones with a positive flow rate, and the minutes it takes
+/// to get from one to another. Valves without flow only matter for the distances.
+struct Network {
+    names: Vec<String>,
+    rates: Vec<u32>,
+    /// minutes from flowing valve `i` to flowing valve `j`, `None` if there is no way.
+    dist: Vec<Vec<Option<u32>>>,
+    /// minutes from the start to every flowing valve.
+    from_start: Vec<Option<u32>>,
 }
 
-#[derive(Eq, Hash, PartialEq)]
-struct Memo2 {
-    valve1: String,
-    time1: u32,
-    valve2: String,
-    time2: u32,
-    on: u64,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Opening {
+    valve: usize,
+    /// the minute the valve is opened in. It releases pressure from the next one on.
+    minute: u32,
 }
 
-struct Dfs2<'a> {
-    valves: &'a HashMap<String, Valve>,
-    distances: &'a Vec<Vec<u32>>,
-    valve1: &'a str,
-    time1: u32,
-    valve2: &'a str,
-    time2: u32,
-    on: u64,
-    memo: &'a mut HashMap<Memo2, u32>,
+/// The valves one agent opens, in order.
+#[derive(Debug, Clone, Default)]
+struct Route {
+    pressure: u32,
+    openings: Vec<Opening>,
 }
 
-fn dfs_2(i: &mut Dfs2) -> u32 {
-    if i.time1 == 0 && i.time2 == 0 {
-        return 0;
-    }
-    let key = Memo2 {
-        valve1: i.valve1.to_string(),
-        time1: i.time1,
-        valve2: i.valve2.to_string(),
-        time2: i.time2,
-        on: i.on,
-    };
-    if let Some(score) = i.memo.get(&key) {
-        return *score;
-    }
-    let mut max_score = 0_u32;
+/// Who opens which valve when, and how much pressure that releases in total.
+#[derive(Debug)]
+struct Plan {
+    pressure: u32,
+    minutes: u32,
+    routes: Vec<Route>,
+}
 
-    let v1 = i.valves.get(&i.valve1.to_string()).unwrap();
-    let v2 = i.valves.get(&i.valve2.to_string()).unwrap();
-    let dists1 = &i.distances[v1.index as usize];
-    let dists2 = &i.distances[v2.index as usize];
+impl Network {
+    /// The best pressure a single agent can release in `minutes` for every set of valves it can
+    /// open in time.
+    fn best_per_set(&self, minutes: u32) -> HashMap<ValveSet, Route> {
+        let mut best = HashMap::new();
+        let mut route = Route::default();
+        self.walk(
+            None,
+            minutes,
+            minutes,
+            &ValveSet::default(),
+            &mut route,
+            &mut best,
+        );
+        best
+    }
 
-    for v in i.valves.values() {
-        if v.rate == 0 || is_set(i.on, v.index) {
-            continue;
+    fn walk(
+        &self,
+        at: Option<usize>,
+        minutes: u32,
+        left: u32,
+        open: &ValveSet,
+        route: &mut Route,
+        best: &mut HashMap<ValveSet, Route>,
+    ) {
+        let entry = best.entry(open.clone()).or_default();
+        if entry.openings.is_empty() || route.pressure > entry.pressure {
+            *entry = route.clone();
         }
 
-        let new_on = set_bit(i.on, v.index);
-
-        let v_dist1 = dists1[v.index as usize];
-        if v_dist1 < u32::MAX && i.time1 as i32 - v_dist1 as i32 >= 1 {
-            let time_left = i.time1 - v_dist1 - 1;
-            let search_score = dfs_2(&mut Dfs2 {
-                valves: i.valves,
-                distances: i.distances,
-                valve1: v.name.as_str(),
-                time1: time_left,
-                valve2: i.valve2,
-                time2: i.time2,
-                on: new_on,
-                memo: i.memo,
-            });
-            max_score = max_score.max(search_score + v.rate * time_left);
-        }
+        for next in 0..self.rates.len() {
+            let dist = match at {
+                Some(at) => self.dist[at][next],
+                None => self.from_start[next],
+            };
+            // walking there and opening it has to leave at least a minute of flow.
+            let Some(left) = dist.and_then(|d| left.checked_sub(d + 1)) else {
+                continue;
+            };
+            if left == 0 || open.contains(next) {
+                continue;
+            }
 
-        let v_dist2 = dists2[v.index as usize];
-        if v_dist2 < u32::MAX && i.time2 as i32 - v_dist2 as i32 >= 1 {
-            let time_left = i.time2 - v_dist2 - 1;
-            let search_score = dfs_2(&mut Dfs2 {
-                valves: i.valves,
-                distances: i.distances,
-                valve1: i.valve1,
-                time1: i.time1,
-                valve2: v.name.as_str(),
-                time2: time_left,
-                on: new_on,
-                memo: i.memo,
+            route.pressure += self.rates[next] * left;
+            route.openings.push(Opening {
+                valve: next,
+                minute: minutes - left,
             });
-            max_score = max_score.max(search_score + v.rate * time_left);
+            self.walk(Some(next), minutes, left, &open.with(next), route, best);
+            route.openings.pop();
+            route.pressure -= self.rates[next] * left;
         }
     }
 
-    i.memo.insert(key, max_score);
-    max_score
+    /// The most pressure `agents` agents working at the same time can release in `minutes`.
+    ///
+    /// Every agent opens a different set of valves, so the best plan combines the best route
+    /// for each of several disjoint sets.
+    fn plan(&self, agents: usize, minutes: u32) -> Plan {
+        let mut routes = self.best_per_set(minutes).into_iter().collect_vec();
+        routes.sort_by_key(|(_, r)| {
+            (
+                Reverse(r.pressure),
+                r.openings.iter().map(|o| o.valve).collect_vec(),
+            )
+        });
+
+        let mut memo = HashMap::new();
+        let (pressure, chosen) = combine(&routes, agents, &ValveSet::default(), &mut memo);
+        Plan {
+            pressure,
+            minutes,
+            routes: chosen.into_iter().map(|i| routes[i].1.clone()).collect(),
+        }
+    }
 }
 
-fn calc_distances(valves: &HashMap<String, Valve>) -> Vec<Vec<u32>> {
-    let mut distances = vec![vec![std::u32::MAX; valves.len()]; valves.len()];
-    for v in valves.values() {
-        for t in &v.tunnels {
-            let t = valves.get(t).unwrap();
-            distances[v.index as usize][t.index as usize] = 1;
+type Combined = (u32, Vec<usize>);
+
+/// The best total of `agents` routes that are disjoint from each other and from `taken`, with
+/// their indices. `routes` are sorted by decreasing pressure.
+fn combine(
+    routes: &[(ValveSet, Route)],
+    agents: usize,
+    taken: &ValveSet,
+    memo: &mut HashMap<(usize, ValveSet), Combined>,
+) -> Combined {
+    if agents == 0 {
+        return (0, vec![]);
+    }
+    if let Some(c) = memo.get(&(agents, taken.clone())) {
+        return c.clone();
+    }
+
+    let mut best: Combined = (0, vec![]);
+    let top = routes.first().map_or(0, |(_, r)| r.pressure);
+    for (i, (set, route)) in routes.iter().enumerate() {
+        // no agent can do better than the best route.
+        if route.pressure + top * (agents as u32 - 1) <= best.0 && !best.1.is_empty() {
+            break;
+        }
+        if !set.is_disjoint(taken) {
+            continue;
+        }
+        let (rest, mut chosen) = combine(routes, agents - 1, &taken.union(set), memo);
+        if best.1.is_empty() || route.pressure + rest > best.0 {
+            chosen.insert(0, i);
+            best = (route.pressure + rest, chosen);
+        }
+        // the best set left for a single agent is the first disjoint one.
+        if agents == 1 {
+            break;
         }
-        distances[v.index as usize][v.index as usize] = 0;
     }
-    let len = valves.len();
-    for i in 0..len {
-        for j in 0..len {
-            for k in 0..len {
-                if distances[j][i] < std::u32::MAX && distances[i][k] < std::u32::MAX {
-                    distances[j][k] = cmp::min(distances[j][k], distances[j][i] + distances[i][k]);
-                }
+
+    memo.insert((agents, taken.clone()), best.clone());
+    best
+}
+
+/// Prints a plan like `minute 2: open DD, releasing 20 × 28 = 560`, one block per agent.
+struct Schedule<'a> {
+    network: &'a Network,
+    plan: &'a Plan,
+}
+
+impl fmt::Display for Schedule<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (n, route) in self.plan.routes.iter().enumerate() {
+            writeln!(f, "agent {} ({} pressure):", n + 1, route.pressure)?;
+            for o in &route.openings {
+                let rate = self.network.rates[o.valve];
+                let left = self.plan.minutes - o.minute;
+                writeln!(
+                    f,
+                    "  minute {}: open {}, releasing {} × {} = {}",
+                    o.minute,
+                    self.network.names[o.valve],
+                    rate,
+                    left,
+                    rate * left
+                )?;
             }
         }
+        write!(f, "total: {}", self.plan.pressure)
     }
+}
 
-    distances
+/// Minutes from `from` to every valve, walking through tunnels.
+fn bfs(tunnels: &[Vec<usize>], from: usize) -> Vec<Option<u32>> {
+    let mut dist = vec![None; tunnels.len()];
+    dist[from] = Some(0);
+    let mut q = VecDeque::from([from]);
+    while let Some(v) = q.pop_front() {
+        let d = dist[v].unwrap();
+        for &t in &tunnels[v] {
+            if dist[t].is_none() {
+                dist[t] = Some(d + 1);
+                q.push_back(t);
+            }
+        }
+    }
+    dist
 }
 
-#[derive(Debug, Clone)]
-struct Valve {
-    name: String,
-    index: u64,
+struct Valve<'a> {
+    name: &'a str,
     rate: u32,
-    tunnels: Vec<String>,
+    tunnels: Vec<&'a str>,
 }
 
-fn parse_line(line: &str, index: u64) -> Result<Valve> {
+fn parse_line(line: &str) -> Result<Valve<'_>> {
     lazy_static! {
         static ref RE: Regex = Regex::new(r"^Valve (?P<name>\w+) has flow rate=(?P<rate>\d+); tunnel[s]? lead[s]? to valve[s]? (?P<tunnels>.*$)").unwrap();
     }
@@ -215,8 +272,7 @@ fn parse_line(line: &str, index: u64) -> Result<Valve> {
     let name = caps
         .name("name")
         .context("invalid input, no name")?
-        .as_str()
-        .to_string();
+        .as_str();
     let rate = caps
         .name("rate")
         .context("invalid input, no rate")?
@@ -227,28 +283,108 @@ fn parse_line(line: &str, index: u64) -> Result<Valve> {
         .context("invalid input, no tunnels")?
         .as_str()
         .split(", ")
-        .map(|s| s.to_string())
         .collect();
 
     Ok(Valve {
         name,
-        index,
         rate,
         tunnels,
     })
 }
 
-fn parse(input: Input) -> Result<Vec<Valve>> {
-    input
+/// Parses the valves and compresses them into a [`Network`] of the flowing ones.
+fn parse(input: Input) -> Result<Network> {
+    let valves = input
         .as_str()
         .lines()
         .enumerate()
-        .map(|(i, l)| parse_line(l, i as u64))
-        .collect()
+        .map(|(n, l)| parse_line(l).with_context(|| format!("line {}: {:?}", n + 1, l)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let ids = valves
+        .iter()
+        .enumerate()
+        .map(|(i, v)| (v.name, i))
+        .collect::<HashMap<_, _>>();
+    let tunnels = valves
+        .iter()
+        .map(|v| {
+            v.tunnels
+                .iter()
+                .map(|t| {
+                    ids.get(t)
+                        .copied()
+                        .ok_or_else(|| anyhow!("valve {} leads to unknown valve {}", v.name, t))
+                })
+                .collect()
+        })
+        .collect::<Result<Vec<Vec<_>>>>()?;
+    let start = *ids.get(START).context("no start valve AA")?;
+
+    let flowing = (0..valves.len())
+        .filter(|&i| valves[i].rate > 0)
+        .collect_vec();
+    let from = |v: usize| {
+        let dist = bfs(&tunnels, v);
+        flowing.iter().map(|&f| dist[f]).collect_vec()
+    };
+    Ok(Network {
+        names: flowing
+            .iter()
+            .map(|&f| valves[f].name.to_string())
+            .collect(),
+        rates: flowing.iter().map(|&f| valves[f].rate).collect(),
+        dist: flowing.iter().map(|&f| from(f)).collect(),
+        from_start: from(start),
+    })
+}
+
+fn show_plan(input: Input, agents: usize, minutes: Option<u32>) -> Result<String> {
+    let network = parse(input)?;
+    let minutes = match minutes {
+        Some(m) => m,
+        None => (agents as u32 - 1)
+            .checked_mul(TEACHING)
+            .and_then(|t| MINUTES.checked_sub(t))
+            .context("no time left after teaching the helpers")?,
+    };
+    let plan = network.plan(agents, minutes);
+    Ok(Schedule {
+        network: &network,
+        plan: &plan,
+    }
+    .to_string())
+}
+
+struct Args {
+    plan: bool,
+    agents: usize,
+    minutes: Option<u32>,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        plan: args.contains("--plan"),
+        agents: args.opt_value_from_str("--agents")?.unwrap_or(1),
+        minutes: args.opt_value_from_str("--minutes")?,
+    })
 }
 
 fn main() {
     let input = &advent_of_code::read_file("inputs", 16);
+
+    // `--plan [--agents <n>] [--minutes <m>]` prints the best schedule for n agents. Without
+    // `--minutes`, teaching every helper takes four of the thirty minutes.
+    let args = parse_args().expect("invalid arguments");
+    if args.plan {
+        match show_plan(Input::new(input), args.agents.max(1), args.minutes) {
+            Ok(r) => println!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -257,6 +393,31 @@ fn main() {
 mod tests {
     use super::*;
 
+    /// Walks a plan and returns the pressure it releases, checking every valve is reachable in
+    /// time and opened only once.
+    fn replay(network: &Network, plan: &Plan) -> u32 {
+        let mut opened = ValveSet::default();
+        let mut total = 0;
+        for route in &plan.routes {
+            let mut at: Option<usize> = None;
+            let mut minute = 0;
+            for o in &route.openings {
+                let dist = match at {
+                    Some(at) => network.dist[at][o.valve],
+                    None => network.from_start[o.valve],
+                };
+                minute += dist.unwrap() + 1;
+                assert_eq!(minute, o.minute);
+                assert!(minute < plan.minutes);
+                assert!(!opened.contains(o.valve));
+                opened = opened.with(o.valve);
+                total += network.rates[o.valve] * (plan.minutes - minute);
+                at = Some(o.valve);
+            }
+        }
+        total
+    }
+
     #[test]
     fn test_part_one() {
         let input = advent_of_code::read_file("examples", 16);
@@ -268,4 +429,62 @@ mod tests {
         let input = advent_of_code::read_file("examples", 16);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 1707);
     }
+
+    #[test]
+    fn test_plan() {
+        let input = advent_of_code::read_file("examples", 16);
+        let network = parse(Input::new(&input)).unwrap();
+        assert_eq!(network.names, ["BB", "CC", "DD", "EE", "HH", "JJ"]);
+
+        let plan = network.plan(1, 30);
+        assert_eq!(replay(&network, &plan), 1651);
+        let order = plan.routes[0]
+            .openings
+            .iter()
+            .map(|o| (network.names[o.valve].as_str(), o.minute))
+            .collect_vec();
+        assert_eq!(
+            order,
+            [
+                ("DD", 2),
+                ("BB", 5),
+                ("JJ", 9),
+                ("HH", 17),
+                ("EE", 21),
+                ("CC", 24)
+            ]
+        );
+
+        let plan = network.plan(2, 26);
+        assert_eq!(plan.routes.len(), 2);
+        assert_eq!(replay(&network, &plan), 1707);
+        // with an agent per valve, everyone walks straight to theirs.
+        assert_eq!(network.plan(6, 26).pressure, 1830);
+        assert_eq!(network.plan(8, 26).pressure, 1830);
+        assert_eq!(network.plan(3, 1).pressure, 0);
+    }
+
+    #[test]
+    fn test_generated() {
+        let input = advent_of_code::generators::generate(16, 1).unwrap();
+        let network = parse(Input::new(&input)).unwrap();
+        let one = network.plan(1, 26);
+        let two = network.plan(2, 26);
+        let three = network.plan(3, 26);
+        assert!(one.pressure <= two.pressure && two.pressure <= three.pressure);
+        for plan in [one, two, three] {
+            assert_eq!(replay(&network, &plan), plan.pressure);
+        }
+    }
+
+    #[test]
+    fn test_invalid_network() {
+        let input = "Valve AA has flow rate=0; tunnel leads to valve BB\n";
+        assert_eq!(
+            format!("{:#}", parse(Input::new(input)).err().unwrap()),
+            "valve AA leads to unknown valve BB"
+        );
+        let input = "Valve BB has flow rate=1; tunnel leads to valve BB\n";
+        assert!(parse(Input::new(input)).is_err());
+    }
 }
@@ -1,12 +1,10 @@
 use std::collections::HashMap;
 
 use advent_of_code::helpers::Input;
-use anyhow::{anyhow, Result};
-use itertools::Itertools;
-
-/*
-Rocks:
+use anyhow::{anyhow, Context, Result};
 
+/// The rocks of the puzzle, in the order they fall.
+const ROCKS: &str = "\
 ####
 
 .#.
@@ -24,286 +22,378 @@ Rocks:
 
 ##
 ##
-*/
-
-const ROCKS: [[[u8; 4]; 4]; 5] = [
-    [[0, 0, 0, 0], [0, 0, 0, 0], [0, 0, 0, 0], [1, 1, 1, 1]],
-    [[0, 0, 0, 0], [0, 1, 0, 0], [1, 1, 1, 0], [0, 1, 0, 0]],
-    [[0, 0, 0, 0], [0, 0, 1, 0], [0, 0, 1, 0], [1, 1, 1, 0]],
-    [[1, 0, 0, 0], [1, 0, 0, 0], [1, 0, 0, 0], [1, 0, 0, 0]],
-    [[0, 0, 0, 0], [0, 0, 0, 0], [1, 1, 0, 0], [1, 1, 0, 0]],
-];
-
-const WIDTH: usize = 7;
-const START_LEFT: usize = 2;
-const START_ABOVE: usize = 3;
+";
+
 const DROPS: usize = 2022;
+const MANY_DROPS: usize = 1_000_000_000_000;
 
 pub fn part_one(input: Input) -> Result<usize> {
-    let jets: Vec<char> = input.as_str().trim().chars().collect();
-    let mut chamber = vec![[0_u8; WIDTH]; START_ABOVE];
-    let mut jet_idx = 0;
-    let mut rock_iter = ROCKS.iter().cycle();
-
-    for _ in 0..DROPS {
-        let rock = rock_iter.next().unwrap();
-        drop_rock(&mut chamber, rock, &jets, &mut jet_idx)?;
-    }
-
-    Ok(top(&chamber))
+    let mut chamber = Chamber::new(Config::default(), parse_shapes(ROCKS)?, parse_jets(input)?)?;
+    Ok(chamber.height_after(DROPS))
 }
 
 pub fn part_two(input: Input) -> Result<usize> {
-    let jets: Vec<char> = input.as_str().trim().chars().collect();
-    let cycle = find_cycle(&jets)?;
-
-    let target: usize = 1000000000000;
-
-    let rem = target - cycle.cycle_start.drops;
-    let cycles = rem / cycle.cycle_drops;
-    let rem = rem % cycle.cycle_drops;
-
-    // simulate one cycle plus remainder
-    let mut chamber = vec![[0_u8; WIDTH]; START_ABOVE];
-    let mut jet_idx = 0;
-    let mut rock_iter = ROCKS.iter().cycle();
-    for _ in 0..(cycle.cycle_start.drops + rem) {
-        let rock = rock_iter.next().unwrap();
-        drop_rock(&mut chamber, rock, &jets, &mut jet_idx)?;
-    }
-
-    Ok(top(&chamber) + cycles * cycle.cycle_height)
+    let mut chamber = Chamber::new(Config::default(), parse_shapes(ROCKS)?, parse_jets(input)?)?;
+    Ok(chamber.height_after(MANY_DROPS))
 }
 
-#[derive(Eq, Hash, PartialEq, Debug)]
-struct CycleKey {
-    rock_idx: usize,
-    jet_idx: usize,
-    snapshot: u128,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Jet {
+    Left,
+    Right,
 }
 
-#[derive(Debug, Clone)]
-struct CycleValue {
-    drops: usize,
-    height: usize,
+/// A rock as one bitmask per row, bottom row first. Bit `i` is the `i`th column from the left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Shape {
+    rows: Vec<u16>,
+    width: usize,
 }
 
-#[derive(Debug)]
-struct Cycle {
-    cycle_height: usize,
-    cycle_drops: usize,
-    cycle_start: CycleValue,
+/// The chamber's dimensions and where new rocks appear.
+#[derive(Debug, Clone, Copy)]
+struct Config {
+    /// at most 16 columns, so a row fits in a `u16`.
+    width: usize,
+    /// empty columns between the left wall and a new rock.
+    spawn_left: usize,
+    /// empty rows between the highest rock and a new one.
+    spawn_above: usize,
 }
 
-fn find_cycle(jets: &Vec<char>) -> Result<Cycle> {
-    let mut chamber = vec![[0_u8; WIDTH]; START_ABOVE];
-    let mut rock_count = 0;
-    let mut jet_idx = 0;
-
-    let mut cycle_map = HashMap::<CycleKey, CycleValue>::new();
-
-    loop {
-        chamber.resize(top(&chamber), [0; WIDTH]);
-        if let Some(snapshot) = snapshot_chamber(&chamber) {
-            let key = CycleKey {
-                rock_idx: rock_count % ROCKS.len(),
-                jet_idx,
-                snapshot,
-            };
-            let value = CycleValue {
-                drops: rock_count,
-                height: top(&chamber),
-            };
-            if let Some(old_value) = cycle_map.get(&key) {
-                return Ok(Cycle {
-                    cycle_height: value.height - old_value.height,
-                    cycle_drops: value.drops - old_value.drops,
-                    cycle_start: old_value.clone(),
-                });
-            }
-            cycle_map.insert(key, value);
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            width: 7,
+            spawn_left: 2,
+            spawn_above: 3,
         }
-
-        drop_rock(
-            &mut chamber,
-            &ROCKS[rock_count % ROCKS.len()],
-            jets,
-            &mut jet_idx,
-        )?;
-        rock_count += 1;
     }
 }
 
-fn snapshot_chamber(chamber: &[[u8; WIDTH]]) -> Option<u128> {
-    if chamber.len() < 20 {
-        return None;
-    }
-
-    let mut snapshot = 0_u128;
-    for i in 0..128 {
-        snapshot |= (chamber[chamber.len() - 1 - (i / WIDTH)][i % WIDTH] as u128) << i;
-    }
+#[derive(Debug, Clone, Copy)]
+struct Falling {
+    shape: usize,
+    x: usize,
+    y: usize,
+}
 
-    Some(snapshot)
+/// The settled rocks, one bitmask per row from the floor up, and the rock still falling.
+struct Chamber {
+    config: Config,
+    shapes: Vec<Shape>,
+    jets: Vec<Jet>,
+    rows: Vec<u16>,
+    /// the jet that pushes next.
+    jet: usize,
+    /// rocks that came to rest.
+    settled: usize,
+    falling: Option<Falling>,
 }
 
-fn drop_rock(
-    chamber: &mut Vec<[u8; WIDTH]>,
-    rock: &[[u8; 4]; 4],
-    jets: &Vec<char>,
-    jet_idx: &mut usize,
-) -> Result<()> {
-    // add rock to chamber
-    let mut vert_off = top(chamber) + START_ABOVE;
-    chamber.resize(vert_off + rock.len(), [0; WIDTH]);
-    for (row_idx, row) in rock.iter().rev().enumerate() {
-        for (col_idx, col) in row.iter().enumerate() {
-            chamber[vert_off + row_idx][col_idx + START_LEFT] = if *col == 1 { 2 } else { 0 };
+impl Chamber {
+    fn new(config: Config, shapes: Vec<Shape>, jets: Vec<Jet>) -> Result<Self> {
+        if config.width > 16 {
+            return Err(anyhow!("the chamber can be at most 16 units wide"));
+        }
+        if shapes.is_empty() || jets.is_empty() {
+            return Err(anyhow!("need at least one rock and one jet"));
         }
+        if let Some(i) = shapes
+            .iter()
+            .position(|s| config.spawn_left + s.width > config.width)
+        {
+            return Err(anyhow!("rock {} does not fit into the chamber", i + 1));
+        }
+        Ok(Self {
+            config,
+            shapes,
+            jets,
+            rows: vec![],
+            jet: 0,
+            settled: 0,
+            falling: None,
+        })
     }
 
-    loop {
-        // push left or right
-        let jet = jets[*jet_idx];
-        *jet_idx = (*jet_idx + 1) % jets.len();
-        match jet {
-            '>' => {
-                if chamber[vert_off..vert_off + 4]
-                    .iter()
-                    .map(can_shift_right)
-                    .all(|x| x)
-                {
-                    chamber[vert_off..vert_off + 4]
-                        .iter_mut()
-                        .for_each(shift_right);
-                }
-            }
-            '<' => {
-                if chamber[vert_off..vert_off + 4]
-                    .iter()
-                    .map(can_shift_left)
-                    .all(|x| x)
-                {
-                    chamber[vert_off..vert_off + 4]
-                        .iter_mut()
-                        .for_each(shift_left);
-                }
-            }
-            _ => Err(anyhow!("Invalid jet: {}", jet))?,
-        };
-
-        // check if stuck
-        if vert_off == 0 || (0..WIDTH).any(|col| !can_shift_down(chamber, col, vert_off)) {
-            for row in chamber[vert_off..vert_off + 4].iter_mut() {
-                for c in row {
-                    if *c == 2 {
-                        *c = 1;
-                    }
-                }
-            }
+    fn height(&self) -> usize {
+        self.rows.len()
+    }
 
-            break;
-        }
+    /// Whether the shape fits at `x`, `y` without hitting a wall or a settled rock.
+    fn fits(&self, shape: usize, x: usize, y: usize) -> bool {
+        let shape = &self.shapes[shape];
+        x + shape.width <= self.config.width
+            && shape.rows.iter().enumerate().all(|(dy, &row)| {
+                self.rows
+                    .get(y + dy)
+                    .is_none_or(|&settled| settled & (row << x) == 0)
+            })
+    }
 
-        // fall down
-        for col in 0..WIDTH {
-            shift_down(chamber, col, vert_off);
-        }
-        vert_off -= 1;
+    /// The falling rock, after putting the next one at its spawn position if there is none.
+    fn spawn(&mut self) -> Falling {
+        *self.falling.get_or_insert(Falling {
+            shape: self.settled % self.shapes.len(),
+            x: self.config.spawn_left,
+            y: self.rows.len() + self.config.spawn_above,
+        })
     }
 
-    Ok(())
-}
+    /// Moves the falling rock one jet push and one unit down, spawning a new one first if
+    /// there is none. Returns whether the rock came to rest.
+    fn step(&mut self) -> bool {
+        let mut rock = self.spawn();
 
-fn top(chamber: &[[u8; WIDTH]]) -> usize {
-    match chamber.iter().rev().position(|row| row.contains(&1)) {
-        Some(x) => chamber.len() - x,
-        None => 0,
-    }
-}
+        let pushed = match self.jets[self.jet] {
+            Jet::Left => rock.x.checked_sub(1),
+            Jet::Right => Some(rock.x + 1),
+        };
+        self.jet = (self.jet + 1) % self.jets.len();
+        if let Some(x) = pushed.filter(|&x| self.fits(rock.shape, x, rock.y)) {
+            rock.x = x;
+        }
 
-fn can_shift_left(row: &[u8; WIDTH]) -> bool {
-    if row[0] == 2 {
-        return false;
+        match rock.y.checked_sub(1) {
+            Some(y) if self.fits(rock.shape, rock.x, y) => {
+                rock.y = y;
+                self.falling = Some(rock);
+                false
+            }
+            _ => {
+                self.settle(rock);
+                true
+            }
+        }
     }
 
-    for i in 0..WIDTH - 1 {
-        if row[i] == 1 && row[i + 1] == 2 {
-            return false;
+    fn settle(&mut self, rock: Falling) {
+        let shape = &self.shapes[rock.shape];
+        let top = rock.y + shape.rows.len();
+        if self.rows.len() < top {
+            self.rows.resize(top, 0);
         }
+        for (dy, &row) in shape.rows.iter().enumerate() {
+            self.rows[rock.y + dy] |= row << rock.x;
+        }
+        // rows a rock left empty below the top don't count towards the height.
+        while self.rows.last() == Some(&0) {
+            self.rows.pop();
+        }
+        self.falling = None;
+        self.settled += 1;
     }
 
-    true
-}
+    fn drop_rock(&mut self) {
+        while !self.step() {}
+    }
 
-fn shift_left(row: &mut [u8; WIDTH]) {
-    for i in 1..WIDTH {
-        if row[i] == 2 {
-            row[i - 1] = 2;
-            row[i] = 0;
+    /// The rows from the lowest cell a falling rock could still reach up to the top. Nothing
+    /// below them can change anymore, so two chambers with the same surface, next rock and next
+    /// jet grow the same way.
+    fn surface(&self) -> Vec<u16> {
+        let full = u16::MAX >> (16 - self.config.width);
+        let mut lowest = self.rows.len();
+        let mut reach = full;
+        // rocks only move sideways and down, so every row is reached from the one above.
+        for y in (0..self.rows.len()).rev() {
+            let open = !self.rows[y] & full;
+            reach &= open;
+            loop {
+                let spread = (reach | reach << 1 | reach >> 1) & open;
+                if spread == reach {
+                    break;
+                }
+                reach = spread;
+            }
+            if reach == 0 {
+                break;
+            }
+            lowest = y;
         }
+        self.rows[lowest..].to_vec()
     }
-}
 
-fn can_shift_right(row: &[u8; WIDTH]) -> bool {
-    if row[WIDTH - 1] == 2 {
-        return false;
+    /// The height of the tower once `rocks` rocks have settled. Stops dropping rocks as soon
+    /// as the chamber repeats its surface at the same rock and jet, and extrapolates from there.
+    fn height_after(&mut self, rocks: usize) -> usize {
+        let mut seen = HashMap::new();
+        let mut heights = vec![self.height()];
+        let start = self.settled;
+        while self.settled < rocks {
+            let key = (self.settled % self.shapes.len(), self.jet, self.surface());
+            if let Some(&first) = seen.get(&key) {
+                let period = self.settled - first;
+                let growth = self.height() - heights[first - start];
+                let (cycles, rest) = ((rocks - first) / period, (rocks - first) % period);
+                return heights[first - start + rest] + cycles * growth;
+            }
+            seen.insert(key, self.settled);
+            self.drop_rock();
+            heights.push(self.height());
+        }
+        self.height()
     }
 
-    for i in (1..WIDTH).rev() {
-        if row[i] == 1 && row[i - 1] == 2 {
-            return false;
+    /// Draws the chamber like the puzzle description does, with `@` for the falling rock and
+    /// `#` for settled ones.
+    fn render(&self) -> String {
+        let falling = self.falling.map(|rock| {
+            let shape = &self.shapes[rock.shape];
+            (
+                rock.y,
+                shape.rows.iter().map(|&r| r << rock.x).collect::<Vec<_>>(),
+            )
+        });
+        let top = falling
+            .as_ref()
+            .map_or(0, |(y, rows)| y + rows.len())
+            .max(self.rows.len());
+
+        let mut out = String::new();
+        for y in (0..top).rev() {
+            let settled = self.rows.get(y).copied().unwrap_or_default();
+            let moving = falling
+                .as_ref()
+                .and_then(|(fy, rows)| rows.get(y.checked_sub(*fy)?))
+                .copied()
+                .unwrap_or_default();
+            out.push('|');
+            for x in 0..self.config.width {
+                out.push(match (settled >> x & 1, moving >> x & 1) {
+                    (_, 1) => '@',
+                    (1, _) => '#',
+                    _ => '.',
+                });
+            }
+            out += "|\n";
         }
+        out += &format!("+{}+\n", "-".repeat(self.config.width));
+        out
     }
+}
 
-    true
+fn parse_jets(input: Input) -> Result<Vec<Jet>> {
+    input
+        .as_str()
+        .trim_end()
+        .chars()
+        .enumerate()
+        .map(|(i, c)| match c {
+            '<' => Ok(Jet::Left),
+            '>' => Ok(Jet::Right),
+            _ => Err(anyhow!("invalid jet {:?} at column {}", c, i + 1)),
+        })
+        .collect()
 }
 
-fn shift_right(row: &mut [u8; WIDTH]) {
-    for i in (0..WIDTH - 1).rev() {
-        if row[i] == 2 {
-            row[i + 1] = 2;
-            row[i] = 0;
-        }
-    }
+/// Parses rocks drawn with `#` and `.`, separated by empty lines.
+fn parse_shapes(s: &str) -> Result<Vec<Shape>> {
+    s.replace("\r\n", "\n")
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .enumerate()
+        .map(|(n, block)| {
+            let mut rows = block
+                .lines()
+                .map(|l| {
+                    l.chars()
+                        .enumerate()
+                        .try_fold(0_u16, |row, (x, c)| match c {
+                            '#' if x < 16 => Ok(row | 1 << x),
+                            '#' => Err(anyhow!("more than 16 units wide")),
+                            '.' => Ok(row),
+                            _ => Err(anyhow!("invalid character {:?}", c)),
+                        })
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("rock {}", n + 1))?;
+            rows.reverse();
+            let mask = rows.iter().fold(0, |acc, r| acc | r);
+            if mask == 0 {
+                return Err(anyhow!("rock {} is empty", n + 1));
+            }
+            Ok(Shape {
+                rows,
+                width: 16 - mask.leading_zeros() as usize,
+            })
+        })
+        .collect()
 }
 
-fn can_shift_down(chamber: &[[u8; WIDTH]], col: usize, vert_off: usize) -> bool {
-    for i in vert_off..chamber.len() {
-        if chamber[i][col] == 2 && chamber[i - 1][col] == 1 {
-            return false;
+/// Drops `rocks` rocks and renders the chamber with the next one falling, `steps` jet pushes
+/// after it appeared.
+fn render_step(
+    input: Input,
+    config: Config,
+    shapes: Option<&str>,
+    rocks: usize,
+    steps: usize,
+) -> Result<String> {
+    let shapes = match shapes {
+        Some(path) => parse_shapes(&std::fs::read_to_string(path)?)?,
+        None => parse_shapes(ROCKS)?,
+    };
+    let mut chamber = Chamber::new(config, shapes, parse_jets(input)?)?;
+    for _ in 0..rocks {
+        chamber.drop_rock();
+    }
+    chamber.spawn();
+    for _ in 0..steps {
+        if chamber.step() {
+            break;
         }
     }
-    true
+    Ok(chamber.render())
 }
 
-fn shift_down(chamber: &mut [[u8; WIDTH]], col: usize, vert_off: usize) {
-    for i in vert_off..chamber.len() {
-        if chamber[i][col] == 2 {
-            chamber[i - 1][col] = 2;
-            chamber[i][col] = 0;
-        }
-    }
+struct Args {
+    rocks: Option<usize>,
+    steps: usize,
+    config: Config,
+    shapes: Option<String>,
 }
 
-fn _print_chamber(chamber: &[[u8; WIDTH]]) {
-    for row in chamber.iter().rev() {
-        let p = row
-            .iter()
-            .map(|c| match c {
-                0 => '.',
-                1 => '#',
-                2 => '@',
-                _ => '?',
-            })
-            .join("");
-        println!("{p}");
-    }
-    println!()
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    let default = Config::default();
+    Ok(Args {
+        rocks: args.opt_value_from_str("--rocks")?,
+        steps: args.opt_value_from_str("--steps")?.unwrap_or(0),
+        config: Config {
+            width: args.opt_value_from_str("--width")?.unwrap_or(default.width),
+            spawn_left: args
+                .opt_value_from_str("--left")?
+                .unwrap_or(default.spawn_left),
+            spawn_above: args
+                .opt_value_from_str("--above")?
+                .unwrap_or(default.spawn_above),
+        },
+        shapes: args.opt_value_from_str("--shapes")?,
+    })
 }
 
 fn main() {
     let input = &advent_of_code::read_file("inputs", 17);
+
+    // `--rocks <n> [--steps <s>] [--width <w>] [--left <x>] [--above <y>] [--shapes <file>]`
+    // draws the chamber after n rocks and s more pushes, with rocks read from a file.
+    let args = parse_args().expect("invalid arguments");
+    if let Some(rocks) = args.rocks {
+        let rendered = render_step(
+            Input::new(input),
+            args.config,
+            args.shapes.as_deref(),
+            rocks,
+            args.steps,
+        );
+        match rendered {
+            Ok(r) => print!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -323,4 +413,76 @@ mod tests {
         let input = advent_of_code::read_file("examples", 17);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 1514285714288);
     }
+
+    #[test]
+    fn test_render() {
+        let input = advent_of_code::read_file("examples", 17);
+        let config = Config::default();
+        assert_eq!(
+            render_step(Input::new(&input), config, None, 0, 0).unwrap(),
+            "|..@@@@.|\n|.......|\n|.......|\n|.......|\n+-------+\n"
+        );
+        assert_eq!(
+            render_step(Input::new(&input), config, None, 0, 1).unwrap(),
+            "|...@@@@|\n|.......|\n|.......|\n+-------+\n"
+        );
+        let expected = [
+            "|....@..|",
+            "|....@..|",
+            "|..@@@..|",
+            "|.......|",
+            "|.......|",
+            "|.......|",
+            "|...#...|",
+            "|..###..|",
+            "|...#...|",
+            "|..####.|",
+            "+-------+",
+        ]
+        .map(|row| row.to_string() + "\n")
+        .concat();
+        assert_eq!(
+            render_step(Input::new(&input), config, None, 2, 0).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_configured_chamber() {
+        let input = advent_of_code::read_file("examples", 17);
+        let jets = parse_jets(Input::new(&input)).unwrap();
+        let shapes = parse_shapes(ROCKS).unwrap();
+        assert_eq!(shapes[1].rows, [0b010, 0b111, 0b010]);
+        assert_eq!(shapes[2].rows, [0b111, 0b100, 0b100]);
+
+        // the extrapolated height matches simply dropping every rock, for other chambers too.
+        for config in [
+            Config::default(),
+            Config {
+                width: 9,
+                spawn_left: 1,
+                spawn_above: 4,
+            },
+            Config {
+                width: 16,
+                spawn_left: 5,
+                spawn_above: 3,
+            },
+        ] {
+            let mut chamber = Chamber::new(config, shapes.clone(), jets.clone()).unwrap();
+            for _ in 0..5000 {
+                chamber.drop_rock();
+            }
+            let mut fresh = Chamber::new(config, shapes.clone(), jets.clone()).unwrap();
+            assert_eq!(fresh.height_after(5000), chamber.height());
+        }
+
+        let wide = Config {
+            width: 3,
+            ..Config::default()
+        };
+        assert!(Chamber::new(wide, shapes, jets).is_err());
+        assert!(parse_shapes("#x#\n").is_err());
+        assert!(parse_jets(Input::new("<<>-")).is_err());
+    }
 }
@@ -1,118 +1,337 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
 use advent_of_code::helpers::Input;
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use itertools::Itertools;
 
-const DIRS: [(i32, i32, i32); 6] = [
-    (1, 0, 0),
-    (-1, 0, 0),
-    (0, 1, 0),
-    (0, -1, 0),
-    (0, 0, 1),
-    (0, 0, -1),
+/// Inputs are `i32`, the wider type leaves room for the air around them.
+type Voxel = [i64; 3];
+
+const DIRS: [Voxel; 6] = [
+    [1, 0, 0],
+    [-1, 0, 0],
+    [0, 1, 0],
+    [0, -1, 0],
+    [0, 0, 1],
+    [0, 0, -1],
 ];
 
-pub fn part_one(input: Input) -> Result<u32> {
-    let cubes = parse_cubes(&input)?;
-    let mut grid = vec![vec![vec![false; 20]; 20]; 20];
-    for (x, y, z) in cubes.iter() {
-        grid[*x][*y][*z] = true;
-    }
-
-    let mut count = 0;
-    for (x, y, z) in cubes {
-        for (dx, dy, dz) in DIRS.iter() {
-            if x as i32 + dx < 0
-                || y as i32 + dy < 0
-                || z as i32 + dz < 0
-                || x as i32 + dx >= grid.len() as i32
-                || y as i32 + dy >= grid[x].len() as i32
-                || z as i32 + dz >= grid[x][y].len() as i32
-                || !grid[(x as i32 + dx) as usize][(y as i32 + dy) as usize]
-                    [(z as i32 + dz) as usize]
-            {
-                count += 1;
-            }
-        }
-    }
+pub fn part_one(input: Input) -> Result<usize> {
+    Ok(parse(input)?.surface_area())
+}
 
-    Ok(count)
+pub fn part_two(input: Input) -> Result<usize> {
+    Ok(parse(input)?.exterior_surface_area())
 }
 
-// hardcode max instead of parsing
-const MAX_DIM: usize = 22;
+/// A droplet as a set of cubes. Only the air next to it is ever looked at, so its size doesn't
+/// depend on how far apart the cubes are.
+struct Droplet {
+    cubes: Vec<Voxel>,
+    lava: HashSet<Voxel>,
+    /// the lowest and highest cube on every line parallel to an axis, keyed by the axis and the
+    /// other two coordinates.
+    lines: HashMap<(usize, i64, i64), (i64, i64)>,
+    /// air enclosed by the droplet.
+    pockets: HashSet<Voxel>,
+}
 
-pub fn part_two(input: Input) -> Result<u32> {
-    let mut cubes = parse_cubes(&input)?;
-    // shift so we don't have to deal with negative indices
-    for cube in cubes.iter_mut() {
-        cube.0 += 1;
-        cube.1 += 1;
-        cube.2 += 1;
-    }
+/// The side of a cube that faces `dir`.
+#[derive(Debug, Clone, Copy)]
+struct Face {
+    cube: Voxel,
+    dir: Voxel,
+}
 
-    let mut grid = vec![vec![vec![false; MAX_DIM]; MAX_DIM]; MAX_DIM];
-    let mut exposed = vec![vec![vec![false; MAX_DIM]; MAX_DIM]; MAX_DIM];
+fn neighbors(v: Voxel) -> impl Iterator<Item = Voxel> {
+    DIRS.into_iter()
+        .map(move |d| [v[0] + d[0], v[1] + d[1], v[2] + d[2]])
+}
 
-    for (x, y, z) in cubes.iter() {
-        grid[*x][*y][*z] = true;
-    }
+fn line_key(v: Voxel, axis: usize) -> (usize, i64, i64) {
+    (axis, v[(axis + 1) % 3], v[(axis + 2) % 3])
+}
 
-    // flood fill to determine exposed faces
-    let mut q = vec![(0_usize, 0_usize, 0_usize)];
-    while !q.is_empty() {
-        let n = q.pop().unwrap();
-        if grid[n.0][n.1][n.2] || exposed[n.0][n.1][n.2] {
+/// Groups of voxels connected through faces.
+fn groups(voxels: &HashSet<Voxel>) -> Vec<Vec<Voxel>> {
+    let mut seen = HashSet::new();
+    let mut groups = vec![];
+    for &start in voxels.iter().sorted() {
+        if !seen.insert(start) {
             continue;
         }
+        let mut group = vec![];
+        let mut stack = vec![start];
+        while let Some(v) = stack.pop() {
+            group.push(v);
+            stack.extend(neighbors(v).filter(|n| voxels.contains(n) && seen.insert(*n)));
+        }
+        group.sort();
+        groups.push(group);
+    }
+    groups
+}
+
+impl Droplet {
+    fn new(cubes: &[Voxel]) -> Self {
+        let cubes = cubes.iter().copied().sorted().dedup().collect_vec();
+        let mut lines = HashMap::new();
+        for &c in &cubes {
+            for axis in 0..3 {
+                let (lo, hi) = lines.entry(line_key(c, axis)).or_insert((c[axis], c[axis]));
+                *lo = c[axis].min(*lo);
+                *hi = c[axis].max(*hi);
+            }
+        }
+        let mut droplet = Self {
+            lava: cubes.iter().copied().collect(),
+            cubes,
+            lines,
+            pockets: HashSet::new(),
+        };
 
-        exposed[n.0][n.1][n.2] = true;
-        for dir in DIRS.iter() {
-            let x = n.0 as i32 + dir.0;
-            let y = n.1 as i32 + dir.1;
-            let z = n.2 as i32 + dir.2;
-            if x >= 0
-                && y >= 0
-                && z >= 0
-                && x < MAX_DIM as i32
-                && y < MAX_DIM as i32
-                && z < MAX_DIM as i32
-            {
-                q.push((x as usize, y as usize, z as usize));
+        let mut outside = HashSet::new();
+        let air = droplet
+            .cubes
+            .iter()
+            .flat_map(|&c| neighbors(c))
+            .filter(|v| !droplet.lava.contains(v))
+            .collect_vec();
+        for v in air {
+            if !outside.contains(&v) && !droplet.pockets.contains(&v) {
+                droplet.flood_air(v, &mut outside);
             }
         }
+        droplet
     }
 
-    let mut count = 0;
-    for n in cubes {
-        for dir in DIRS.iter() {
-            let x = (n.0 as i32 + dir.0) as usize;
-            let y = (n.1 as i32 + dir.1) as usize;
-            let z = (n.2 as i32 + dir.2) as usize;
+    /// Whether nothing blocks the view from `v` to infinity along one of the axes.
+    fn has_free_ray(&self, v: Voxel) -> bool {
+        (0..3).any(|axis| {
+            self.lines
+                .get(&line_key(v, axis))
+                .is_none_or(|&(lo, hi)| v[axis] < lo || v[axis] > hi)
+        })
+    }
 
-            if !grid[x][y][z] && exposed[x][y][z] {
-                count += 1;
+    /// Fills the air connected to `start` until it finds a way out. Everything filled goes to
+    /// `outside` if it did, and to the pockets otherwise.
+    fn flood_air(&mut self, start: Voxel, outside: &mut HashSet<Voxel>) {
+        let mut seen = HashSet::from([start]);
+        let mut stack = vec![start];
+        let mut escaped = false;
+        while let Some(v) = stack.pop() {
+            if outside.contains(&v) || self.has_free_ray(v) {
+                escaped = true;
+                break;
+            }
+            for n in neighbors(v) {
+                if !self.lava.contains(&n) && seen.insert(n) {
+                    stack.push(n);
+                }
             }
         }
+        if escaped {
+            outside.extend(seen);
+        } else {
+            self.pockets.extend(seen);
+        }
+    }
+
+    fn is_lava(&self, v: Voxel) -> bool {
+        self.lava.contains(&v)
     }
 
-    Ok(count)
+    /// Whether `v` is air connected to the outside.
+    fn is_outside(&self, v: Voxel) -> bool {
+        !self.is_lava(v) && !self.pockets.contains(&v)
+    }
+
+    /// Cube sides that don't touch another cube.
+    fn faces(&self) -> impl Iterator<Item = Face> + '_ {
+        self.cubes.iter().flat_map(move |&cube| {
+            DIRS.into_iter()
+                .map(move |dir| Face { cube, dir })
+                .filter(|f| !self.is_lava(f.neighbor()))
+        })
+    }
+
+    fn surface_area(&self) -> usize {
+        self.faces().count()
+    }
+
+    /// Sides that steam from outside can reach, leaving out those facing air pockets.
+    fn exterior_faces(&self) -> impl Iterator<Item = Face> + '_ {
+        self.faces().filter(|f| self.is_outside(f.neighbor()))
+    }
+
+    fn exterior_surface_area(&self) -> usize {
+        self.exterior_faces().count()
+    }
+
+    /// Air trapped inside the droplet, one group per pocket.
+    fn air_pockets(&self) -> Vec<Vec<Voxel>> {
+        groups(&self.pockets)
+    }
+
+    /// The separate pieces the droplet consists of.
+    fn components(&self) -> Vec<Vec<Voxel>> {
+        groups(&self.lava)
+    }
 }
 
-fn parse_cubes(input: &Input) -> Result<Vec<(usize, usize, usize)>> {
-    input
+impl Face {
+    fn neighbor(&self) -> Voxel {
+        [0, 1, 2].map(|axis| self.cube[axis] + self.dir[axis])
+    }
+
+    fn normal(&self) -> [f32; 3] {
+        self.dir.map(|d| d as f32)
+    }
+
+    /// The corners of the face, counter-clockwise when looking at it from outside the cube.
+    fn corners(&self) -> [Voxel; 4] {
+        let axis = self.dir.iter().position(|&d| d != 0).unwrap();
+        let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+        let mut base = self.cube;
+        if self.dir[axis] > 0 {
+            base[axis] += 1;
+        }
+        let offset = |db: i64, dc: i64| {
+            let mut v = base;
+            v[b] += db;
+            v[c] += dc;
+            v
+        };
+        let corners = [offset(0, 0), offset(1, 0), offset(1, 1), offset(0, 1)];
+        if self.dir[axis] > 0 {
+            corners
+        } else {
+            [corners[0], corners[3], corners[2], corners[1]]
+        }
+    }
+}
+
+/// A Wavefront OBJ mesh of the faces, with one quad per face and shared vertices.
+fn to_obj(faces: impl Iterator<Item = Face>) -> String {
+    let mut vertices = HashMap::new();
+    let mut out = String::new();
+    let mut quads = String::new();
+    for face in faces {
+        let ids = face.corners().map(|corner| {
+            let next = vertices.len() + 1;
+            *vertices.entry(corner).or_insert_with(|| {
+                writeln!(out, "v {} {} {}", corner[0], corner[1], corner[2]).unwrap();
+                next
+            })
+        });
+        writeln!(quads, "f {} {} {} {}", ids[0], ids[1], ids[2], ids[3]).unwrap();
+    }
+    out + &quads
+}
+
+/// An ASCII STL mesh of the faces, two triangles per face.
+fn to_stl(faces: impl Iterator<Item = Face>) -> String {
+    let mut out = String::from("solid droplet\n");
+    for face in faces {
+        let [n0, n1, n2] = face.normal();
+        let c = face.corners();
+        for triangle in [[c[0], c[1], c[2]], [c[0], c[2], c[3]]] {
+            writeln!(out, "facet normal {} {} {}", n0, n1, n2).unwrap();
+            out += "  outer loop\n";
+            for v in triangle {
+                writeln!(out, "    vertex {} {} {}", v[0], v[1], v[2]).unwrap();
+            }
+            out += "  endloop\nendfacet\n";
+        }
+    }
+    out + "endsolid droplet\n"
+}
+
+fn parse(input: Input) -> Result<Droplet> {
+    let cubes = input
         .as_str()
         .lines()
-        .map(|l| {
+        .enumerate()
+        .map(|(n, l)| {
             l.split(',')
-                .map(|n| n.parse::<usize>().unwrap())
-                .collect_tuple()
-                .context("invalid input")
+                .map(|c| c.trim().parse::<i32>().map(i64::from))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(anyhow::Error::from)
+                .and_then(|c| c.try_into().map_err(|_| anyhow!("expected x,y,z")))
+                .with_context(|| format!("line {}: {:?}", n + 1, l))
         })
-        .collect::<Result<_>>()
+        .collect::<Result<Vec<Voxel>>>()?;
+    Ok(Droplet::new(&cubes))
+}
+
+fn stats(input: Input) -> Result<String> {
+    let droplet = parse(input)?;
+    let pockets = droplet.air_pockets();
+    Ok(format!(
+        "surface area: {}\nexterior surface area: {}\nair pockets: {} ({} voxels)\ncomponents: {}",
+        droplet.surface_area(),
+        droplet.exterior_surface_area(),
+        pockets.len(),
+        pockets.iter().map(Vec::len).sum::<usize>(),
+        droplet.components().len()
+    ))
+}
+
+fn export(input: Input, format: &str, exterior: bool) -> Result<String> {
+    let droplet = parse(input)?;
+    let faces = || -> Box<dyn Iterator<Item = Face>> {
+        if exterior {
+            Box::new(droplet.exterior_faces())
+        } else {
+            Box::new(droplet.faces())
+        }
+    };
+    match format {
+        "obj" => Ok(to_obj(faces())),
+        "stl" => Ok(to_stl(faces())),
+        _ => Err(anyhow!(
+            "unknown mesh format {:?}, expected obj or stl",
+            format
+        )),
+    }
+}
+
+struct Args {
+    stats: bool,
+    export: Option<String>,
+    exterior: bool,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        stats: args.contains("--stats"),
+        export: args.opt_value_from_str("--export")?,
+        exterior: args.contains("--exterior"),
+    })
 }
 
 fn main() {
     let input = &advent_of_code::read_file("inputs", 18);
+
+    // `--stats` describes the droplet, `--export obj|stl [--exterior]` prints it as a mesh,
+    // optionally without the faces around air pockets.
+    let args = parse_args().expect("invalid arguments");
+    let report = match (args.stats, &args.export) {
+        (true, _) => Some(stats(Input::new(input))),
+        (_, Some(format)) => Some(export(Input::new(input), format, args.exterior)),
+        _ => None,
+    };
+    if let Some(report) = report {
+        match report {
+            Ok(r) => println!("{}", r.trim_end()),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -132,4 +351,80 @@ mod tests {
         let input = advent_of_code::read_file("examples", 18);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 58);
     }
+
+    #[test]
+    fn test_geometry() {
+        let input = advent_of_code::read_file("examples", 18);
+        let droplet = parse(Input::new(&input)).unwrap();
+        assert_eq!(droplet.air_pockets(), [vec![[2, 2, 5]]]);
+        // 2,2,6 and the four cubes around the pocket only touch the rest along edges.
+        assert_eq!(droplet.components().len(), 6);
+
+        // a hollow 3x3x3 cube beyond the old 20 unit grid, next to a separate cube at negative
+        // coordinates.
+        let mut cubes = (0..3)
+            .map(|_| 20..23)
+            .multi_cartesian_product()
+            .map(|c| [c[0], c[1], c[2]])
+            .filter(|&c| c != [21, 21, 21])
+            .collect_vec();
+        cubes.push([-3, -3, -3]);
+        let droplet = Droplet::new(&cubes);
+        assert_eq!(droplet.surface_area(), 6 * 9 + 6 + 6);
+        assert_eq!(droplet.exterior_surface_area(), 6 * 9 + 6);
+        assert_eq!(droplet.air_pockets().len(), 1);
+        assert_eq!(droplet.components().len(), 2);
+
+        // a cube floating in a pocket of a hollow 5x5x5 cube.
+        let shell = (0..3)
+            .map(|_| 0..5)
+            .multi_cartesian_product()
+            .map(|c| [c[0], c[1], c[2]])
+            .filter(|c| c.iter().any(|&x| x == 0 || x == 4) || *c == [2, 2, 2])
+            .collect_vec();
+        let droplet = Droplet::new(&shell);
+        assert_eq!(droplet.surface_area(), 6 * 25 + 6 * 9 + 6);
+        assert_eq!(droplet.exterior_surface_area(), 6 * 25);
+        assert_eq!(droplet.air_pockets()[0].len(), 26);
+        assert_eq!(droplet.components().len(), 2);
+
+        // far apart cubes only cost as much as close ones.
+        for far in [600, i32::MAX.into()] {
+            let droplet = Droplet::new(&[[0, 0, 0], [far, far, far], [-far, 0, far]]);
+            assert_eq!(droplet.exterior_surface_area(), 18);
+            assert_eq!(droplet.components().len(), 3);
+        }
+        for extreme in [i32::MIN, i32::MIN + 1, i32::MAX - 1, i32::MAX] {
+            let droplet = Droplet::new(&[[extreme.into(), 0, 0]]);
+            assert_eq!(droplet.exterior_surface_area(), 6);
+            assert_eq!(to_obj(droplet.faces()).lines().count(), 8 + 6);
+        }
+        assert!(parse(Input::new("1,2\n")).is_err());
+        assert_eq!(parse(Input::new("")).unwrap().surface_area(), 0);
+    }
+
+    #[test]
+    fn test_export() {
+        let droplet = Droplet::new(&[[0, 0, 0]]);
+        let obj = to_obj(droplet.faces());
+        assert_eq!(obj.lines().filter(|l| l.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 6);
+        let stl = to_stl(droplet.faces());
+        assert_eq!(stl.matches("facet normal").count(), 12);
+
+        // every corner is seen counter-clockwise from outside, so the normal points outwards.
+        for face in droplet.faces() {
+            let [a, b, _, d] = face.corners();
+            let (u, v) = (
+                [0, 1, 2].map(|i| b[i] - a[i]),
+                [0, 1, 2].map(|i| d[i] - a[i]),
+            );
+            let cross = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            assert_eq!(cross, face.dir);
+        }
+    }
 }
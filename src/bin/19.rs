@@ -1,162 +1,419 @@
-use std::{collections::VecDeque, str::FromStr};
+use std::{fmt::Write, str::FromStr, thread};
 
 use advent_of_code::helpers::Input;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use itertools::Itertools;
 
-// most of this is from dphil's solution
-// https://github.com/dphilipson/advent-of-code-2022/blob/master/src/days/day19.rs
-// Just worked through it on my own for understanding
+const MINUTES: u32 = 24;
+const MORE_MINUTES: u32 = 32;
 
 pub fn part_one(input: Input) -> Result<u32> {
-    Ok(input
-        .as_str()
-        .lines()
-        .map(|line| {
-            let bp = line.parse::<Blueprint>()?;
-            let score = score_blueprint(&bp, 24)?;
-            Ok(bp.id * score)
-        })
-        .collect::<Result<Vec<_>>>()?
+    let blueprints = parse(input)?;
+    let plans = optimize_all(&blueprints, MINUTES);
+    Ok(blueprints
         .iter()
+        .zip(plans)
+        .map(|(bp, plan)| bp.id * plan.geodes)
         .sum())
 }
 
 pub fn part_two(input: Input) -> Result<u32> {
-    Ok(input
-        .as_str()
-        .lines()
-        .take(3)
-        .map(|line| {
-            let bp = line.parse::<Blueprint>()?;
-            let score = score_blueprint(&bp, 32)?;
-            Ok(score)
-        })
-        .collect::<Result<Vec<_>>>()?
-        .iter()
-        .product())
+    let mut blueprints = parse(input)?;
+    blueprints.truncate(3);
+    let plans = optimize_all(&blueprints, MORE_MINUTES);
+    Ok(plans.iter().map(|plan| plan.geodes).product())
 }
 
+/// Robot types and what they cost. Every robot type mines its own resource. There is one
+/// robot of the first type at the start, and the resource of the last one is to be maximized.
 #[derive(Clone, Debug)]
 struct Blueprint {
     id: u32,
-    costs: [[u32; 4]; 4],
+    resources: Vec<String>,
+    /// `costs[robot][resource]`.
+    costs: Vec<Vec<u32>>,
+}
+
+impl Blueprint {
+    fn goal(&self) -> usize {
+        self.resources.len() - 1
+    }
+
+    /// How many robots of every type are useful: more than the most any robot costs of a
+    /// resource can't be spent in a minute.
+    fn max_robots(&self) -> Vec<u32> {
+        (0..self.resources.len())
+            .map(|r| match r == self.goal() {
+                true => u32::MAX,
+                false => self.costs.iter().map(|c| c[r]).max().unwrap_or_default(),
+            })
+            .collect()
+    }
 }
 
 #[derive(Clone, Debug)]
 struct State {
     time: u32,
-    robots: [u32; 4],
-    resources: [u32; 4],
+    robots: Vec<u32>,
+    stock: Vec<u32>,
 }
 
 impl State {
-    fn robot_wait(&self, costs: &[u32; 4]) -> Option<u32> {
+    /// Minutes until a robot costing `costs` is done, `None` if a resource it needs is never
+    /// mined.
+    fn robot_wait(&self, costs: &[u32]) -> Option<u32> {
         let mut wait = 0;
-        for (r, c) in costs.iter().enumerate() {
-            let need = c.saturating_sub(self.resources[r]);
+        for (r, &c) in costs.iter().enumerate() {
+            let need = c.saturating_sub(self.stock[r]);
             if need == 0 {
                 continue;
             }
             if self.robots[r] == 0 {
                 return None;
             }
-            wait = wait.max((need + self.robots[r] - 1) / self.robots[r]);
+            wait = wait.max(need.div_ceil(self.robots[r]));
         }
 
-        Some(wait + 1) // one cycle to build robot
+        Some(wait + 1) // one minute to build the robot
     }
+}
 
-    // ty dphil
-    fn best_possible(&self, time_limit: u32) -> u32 {
-        let rem = time_limit - self.time;
-        // cur +
-        self.resources[3] + self.robots[3] * rem + rem * (rem + 1) / 2
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Build {
+    /// the minute the robot is built in. It starts mining in the next one.
+    minute: u32,
+    robot: usize,
 }
 
-// ty dphil
-fn max_robots_needed(costs: &[[u32; 4]; 4]) -> [u32; 4] {
-    let mut out = [0; 4];
-    out[3] = u32::MAX;
-    for i in 0..3 {
-        let max = costs.iter().map(|c| c[i]).max().unwrap();
-        out[i] = max;
-    }
-    out
+#[derive(Debug, Clone)]
+struct Plan {
+    geodes: u32,
+    builds: Vec<Build>,
 }
 
-fn score_blueprint(bp: &Blueprint, time_limit: u32) -> Result<u32> {
-    let mut best_score = 0;
-    let mut q = VecDeque::from([State {
-        time: 0,
-        robots: [1, 0, 0, 0],
-        resources: [0; 4],
-    }]);
+/// A depth-first search over which robot to build next, skipping the minutes spent waiting for
+/// it.
+struct Search<'a> {
+    bp: &'a Blueprint,
+    minutes: u32,
+    max_robots: Vec<u32>,
+    builds: Vec<Build>,
+    best: Plan,
+}
 
-    let max_robots = max_robots_needed(&bp.costs);
+impl Search<'_> {
+    /// The most the goal resource could reach from `state` if every robot type had its own
+    /// stock and factory: each builds its robot as soon as its stock allows, in parallel.
+    fn upper_bound(&self, state: &State) -> u32 {
+        let goal = self.bp.goal();
+        let mut robots = state.robots.clone();
+        let mut pools = vec![state.stock.clone(); robots.len()];
+        // never spent, in case the goal resource buys robots too.
+        let mut total = state.stock[goal];
+        for _ in state.time..self.minutes {
+            let built = (0..robots.len())
+                .filter(|&t| {
+                    self.bp.costs[t]
+                        .iter()
+                        .zip(&pools[t])
+                        .all(|(c, have)| c <= have)
+                })
+                .collect_vec();
+            for pool in pools.iter_mut() {
+                pool.iter_mut().zip(&robots).for_each(|(p, r)| *p += r);
+            }
+            total += robots[goal];
+            for t in built {
+                pools[t]
+                    .iter_mut()
+                    .zip(&self.bp.costs[t])
+                    .for_each(|(p, c)| *p -= c);
+                robots[t] += 1;
+            }
+        }
+        total
+    }
 
-    while let Some(state) = q.pop_front() {
-        if state.best_possible(time_limit) <= best_score {
-            continue;
+    fn dfs(&mut self, state: State) {
+        let goal = self.bp.goal();
+        let idle = state.stock[goal] + state.robots[goal] * (self.minutes - state.time);
+        if idle > self.best.geodes || self.best.builds.is_empty() && idle == self.best.geodes {
+            self.best = Plan {
+                geodes: idle,
+                builds: self.builds.clone(),
+            };
+        }
+        if self.upper_bound(&state) <= self.best.geodes {
+            return;
         }
 
-        // try to build each robot type
-        #[allow(clippy::needless_range_loop)]
-        for robot in 0..4 {
-            if state.robots[robot] >= max_robots[robot] {
+        // robots closer to the goal first, so good plans and tight bounds are found early.
+        for robot in (0..self.bp.costs.len()).rev() {
+            if state.robots[robot] >= self.max_robots[robot] {
+                continue;
+            }
+            let Some(wait) = state.robot_wait(&self.bp.costs[robot]) else {
+                continue;
+            };
+            // a robot built in the last minute never mines anything.
+            if state.time + wait >= self.minutes {
                 continue;
             }
 
-            // determine how long we need to wait for the resources
-            if let Some(wait) = state.robot_wait(&bp.costs[robot]) {
-                if state.time + wait > time_limit {
-                    continue;
-                }
+            let mut next = state.clone();
+            next.time += wait;
+            for r in 0..next.stock.len() {
+                next.stock[r] += wait * next.robots[r];
+                next.stock[r] -= self.bp.costs[robot][r];
+            }
+            next.robots[robot] += 1;
 
-                // calculate new state after waiting
-                let mut new_state = state.clone();
-                new_state.time += wait; // time to wait for resources
-                for resource in 0..4 {
-                    new_state.resources[resource] += wait * new_state.robots[resource]; // accrue during wait
-                    new_state.resources[resource] -= bp.costs[robot][resource]; // cost to build
-                }
-                new_state.robots[robot] += 1;
+            self.builds.push(Build {
+                minute: next.time,
+                robot,
+            });
+            self.dfs(next);
+            self.builds.pop();
+        }
+    }
+}
 
-                // calculate best score this config of robots can achieve by doing nothing
-                best_score = best_score.max(
-                    new_state.resources[3] + new_state.robots[3] * (time_limit - new_state.time),
-                );
+/// The plan that ends up with the most of the goal resource after `minutes`.
+fn optimize(bp: &Blueprint, minutes: u32) -> Plan {
+    let mut robots = vec![0; bp.resources.len()];
+    robots[0] = 1;
+    let mut search = Search {
+        bp,
+        minutes,
+        max_robots: bp.max_robots(),
+        builds: vec![],
+        best: Plan {
+            geodes: 0,
+            builds: vec![],
+        },
+    };
+    search.dfs(State {
+        time: 0,
+        robots,
+        stock: vec![0; bp.resources.len()],
+    });
+    search.best
+}
+
+/// Optimizes every blueprint on its own thread.
+fn optimize_all(blueprints: &[Blueprint], minutes: u32) -> Vec<Plan> {
+    thread::scope(|s| {
+        let handles = blueprints
+            .iter()
+            .map(|bp| s.spawn(move || optimize(bp, minutes)))
+            .collect_vec();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    })
+}
+
+/// Tells what happens every minute of a plan, the way the puzzle description does.
+fn narrate(bp: &Blueprint, builds: &[Build], minutes: u32) -> Result<String> {
+    let n = bp.resources.len();
+    let robot_name = |r: usize| match bp.resources[r].as_str() {
+        "geode" => "geode-cracking robot".to_string(),
+        name => format!("{}-collecting robot", name),
+    };
+    let amount = |count: u32, r: usize| match bp.resources[r].as_str() {
+        "geode" if count == 1 => "1 open geode".to_string(),
+        "geode" => format!("{} open geodes", count),
+        name => format!("{} {}", count, name),
+    };
 
-                q.push_back(new_state);
+    let mut robots = vec![0; n];
+    robots[0] = 1;
+    let mut stock = vec![0; n];
+    let mut builds = builds.iter().peekable();
+    let mut out = String::new();
+    for minute in 1..=minutes {
+        writeln!(out, "== Minute {} ==", minute)?;
+
+        let build = builds.next_if(|b| b.minute == minute);
+        if let Some(b) = build {
+            let costs = &bp.costs[b.robot];
+            if costs.iter().zip(&stock).any(|(c, have)| c > have) {
+                return Err(anyhow!("can't afford the robot built in minute {}", minute));
             }
+            stock.iter_mut().zip(costs).for_each(|(s, c)| *s -= c);
+            let spent = (0..n)
+                .filter(|&r| costs[r] > 0)
+                .map(|r| format!("{} {}", costs[r], bp.resources[r]))
+                .join(" and ");
+            let name = robot_name(b.robot);
+            let article = if name.starts_with(['a', 'e', 'i', 'o', 'u']) {
+                "an"
+            } else {
+                "a"
+            };
+            writeln!(
+                out,
+                "Spend {} to start building {} {}.",
+                spent, article, name
+            )?;
+        }
+
+        for r in (0..n).filter(|&r| robots[r] > 0) {
+            stock[r] += robots[r];
+            let (plural, verb) = match (robots[r], bp.resources[r].as_str()) {
+                (1, "geode") => ("", "cracks"),
+                (_, "geode") => ("s", "crack"),
+                (1, _) => ("", "collects"),
+                _ => ("s", "collect"),
+            };
+            let mined = match bp.resources[r].as_str() {
+                "geode" if robots[r] == 1 => "1 geode".to_string(),
+                "geode" => format!("{} geodes", robots[r]),
+                name => format!("{} {}", robots[r], name),
+            };
+            writeln!(
+                out,
+                "{} {}{} {} {}; you now have {}.",
+                robots[r],
+                robot_name(r),
+                plural,
+                verb,
+                mined,
+                amount(stock[r], r)
+            )?;
         }
-    }
 
-    Ok(best_score)
+        if let Some(b) = build {
+            robots[b.robot] += 1;
+            writeln!(
+                out,
+                "The new {} is ready; you now have {} of them.",
+                robot_name(b.robot),
+                robots[b.robot]
+            )?;
+        }
+        out.push('\n');
+    }
+    if let Some(b) = builds.next() {
+        return Err(anyhow!(
+            "robot built in minute {} is out of order",
+            b.minute
+        ));
+    }
+    Ok(out)
 }
 
 impl FromStr for Blueprint {
     type Err = anyhow::Error;
 
+    /// Parses `Blueprint <id>: Each <type> robot costs <n> <resource> and <n> <resource>. ...`
+    /// with any number of robot types and costs.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let parsed = sscanf::sscanf!(s, "Blueprint {u32}: Each ore robot costs {u32} ore. Each clay robot costs {u32} ore. Each obsidian robot costs {u32} ore and {u32} clay. Each geode robot costs {u32} ore and {u32} obsidian.");
-        match parsed {
-            Ok(parsed) => Ok(Self {
-                id: parsed.0,
-                costs: [
-                    [parsed.1, 0, 0, 0],
-                    [parsed.2, 0, 0, 0],
-                    [parsed.3, parsed.4, 0, 0],
-                    [parsed.5, 0, parsed.6, 0],
-                ],
-            }),
-            Err(_) => Err(anyhow!("Faliure to parse blueprint: {}", s)),
+        let (header, body) = s.split_once(':').context("expected \"Blueprint <id>:\"")?;
+        let id = header
+            .trim()
+            .strip_prefix("Blueprint ")
+            .context("expected \"Blueprint <id>:\"")?
+            .parse()?;
+
+        let mut robots = vec![];
+        for sentence in body.split('.').map(str::trim).filter(|s| !s.is_empty()) {
+            let (robot, costs) = sentence
+                .strip_prefix("Each ")
+                .and_then(|s| s.split_once(" robot costs "))
+                .with_context(|| format!("expected \"Each <type> robot costs\": {:?}", sentence))?;
+            let costs = costs
+                .split(" and ")
+                .flat_map(|c| c.split(", "))
+                .map(|c| {
+                    let (n, resource) = c.trim().split_once(' ').context("expected a cost")?;
+                    Ok((n.parse::<u32>()?, resource))
+                })
+                .collect::<Result<Vec<_>>>()
+                .with_context(|| format!("{} robot", robot))?;
+            robots.push((robot, costs));
+        }
+        if robots.is_empty() {
+            return Err(anyhow!("no robots"));
         }
+
+        let resources = robots.iter().map(|(r, _)| r.to_string()).collect_vec();
+        if !resources.iter().all_unique() {
+            return Err(anyhow!("a robot type is listed twice"));
+        }
+        let costs = robots
+            .iter()
+            .map(|(_, costs)| {
+                let mut row = vec![0; resources.len()];
+                for (n, resource) in costs {
+                    let r = resources
+                        .iter()
+                        .position(|name| name == resource)
+                        .with_context(|| format!("no robot collects {}", resource))?;
+                    row[r] += n;
+                }
+                Ok(row)
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            id,
+            resources,
+            costs,
+        })
     }
 }
 
+/// Blueprints may be wrapped over several lines, each one starts with `Blueprint`.
+fn parse(input: Input) -> Result<Vec<Blueprint>> {
+    let text = input.as_str().split_whitespace().join(" ");
+    text.split("Blueprint ")
+        .filter(|s| !s.is_empty())
+        .enumerate()
+        .map(|(n, s)| {
+            format!("Blueprint {}", s)
+                .parse()
+                .with_context(|| format!("blueprint {}", n + 1))
+        })
+        .collect()
+}
+
+fn explain(input: Input, id: u32, minutes: u32) -> Result<String> {
+    let bp = parse(input)?
+        .into_iter()
+        .find(|bp| bp.id == id)
+        .with_context(|| format!("no blueprint {}", id))?;
+    let plan = optimize(&bp, minutes);
+    let goal = &bp.resources[bp.goal()];
+    let summary = format!("{} after {} minutes: {}", goal, minutes, plan.geodes);
+    Ok(narrate(&bp, &plan.builds, minutes)? + &summary)
+}
+
+struct Args {
+    explain: Option<u32>,
+    minutes: u32,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        explain: args.opt_value_from_str("--explain")?,
+        minutes: args.opt_value_from_str("--minutes")?.unwrap_or(MINUTES),
+    })
+}
+
 fn main() {
     let input = &advent_of_code::read_file("inputs", 19);
+
+    // `--explain <id> [--minutes <m>]` tells minute by minute how the best plan for a blueprint
+    // plays out.
+    let args = parse_args().expect("invalid arguments");
+    if let Some(id) = args.explain {
+        match explain(Input::new(input), id, args.minutes) {
+            Ok(r) => println!("{}", r),
+            Err(e) => eprintln!("{:#}", e),
+        }
+        return;
+    }
+
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -176,4 +433,81 @@ mod tests {
         let input = advent_of_code::read_file("examples", 19);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 3472);
     }
+
+    #[test]
+    fn test_narrate() {
+        let input = advent_of_code::read_file("examples", 19);
+        let bp = &parse(Input::new(&input)).unwrap()[0];
+        // the plan from the puzzle description.
+        let builds = [
+            (3, 1),
+            (5, 1),
+            (7, 1),
+            (11, 2),
+            (12, 1),
+            (15, 2),
+            (18, 3),
+            (21, 3),
+        ]
+        .map(|(minute, robot)| Build { minute, robot });
+        let text = narrate(bp, &builds, 24).unwrap();
+        for expected in [
+            "== Minute 3 ==\n\
+             Spend 2 ore to start building a clay-collecting robot.\n\
+             1 ore-collecting robot collects 1 ore; you now have 1 ore.\n\
+             The new clay-collecting robot is ready; you now have 1 of them.\n",
+            "== Minute 11 ==\n\
+             Spend 3 ore and 14 clay to start building an obsidian-collecting robot.\n\
+             1 ore-collecting robot collects 1 ore; you now have 2 ore.\n\
+             3 clay-collecting robots collect 3 clay; you now have 4 clay.\n\
+             The new obsidian-collecting robot is ready; you now have 1 of them.\n",
+            "== Minute 24 ==\n\
+             1 ore-collecting robot collects 1 ore; you now have 6 ore.\n\
+             4 clay-collecting robots collect 4 clay; you now have 41 clay.\n\
+             2 obsidian-collecting robots collect 2 obsidian; you now have 8 obsidian.\n\
+             2 geode-cracking robots crack 2 geodes; you now have 9 open geodes.\n",
+        ] {
+            assert!(text.contains(expected), "missing:\n{}", expected);
+        }
+
+        let plan = optimize(bp, 24);
+        assert_eq!(plan.geodes, 9);
+        let text = narrate(bp, &plan.builds, 24).unwrap();
+        assert!(text.ends_with("you now have 9 open geodes.\n\n"));
+
+        let too_early = [Build {
+            minute: 1,
+            robot: 1,
+        }];
+        assert!(narrate(bp, &too_early, 24).is_err());
+    }
+
+    #[test]
+    fn test_grammar() {
+        let bp: Blueprint = "Blueprint 7: Each wood robot costs 1 wood. \
+                             Each stone robot costs 2 wood, 1 stone and 1 wood. \
+                             Each gem robot costs 3 stone."
+            .parse()
+            .unwrap();
+        assert_eq!(bp.resources, ["wood", "stone", "gem"]);
+        assert_eq!(bp.costs, [vec![1, 0, 0], vec![3, 1, 0], vec![0, 3, 0]]);
+        // stone robots need a stone robot first, so there is never any stone.
+        assert_eq!(optimize(&bp, 20).geodes, 0);
+
+        let bp: Blueprint = "Blueprint 2: Each ore robot costs 1 ore.".parse().unwrap();
+        // with robots bought in minutes 2, 3 and 4, 1 + 1 + 2 + 3 + 4 ore are mined and 3 spent.
+        assert_eq!(optimize(&bp, 5).geodes, 8);
+
+        assert!("Blueprint 1: Each ore robot costs 2 clay."
+            .parse::<Blueprint>()
+            .is_err());
+        assert!("Blueprint x: Each ore robot costs 2 ore."
+            .parse::<Blueprint>()
+            .is_err());
+        // the puzzle description wraps blueprints over several lines.
+        let wrapped =
+            "Blueprint 1:\n  Each ore robot costs 4 ore.\n  Each geode robot costs 2 ore.\n\n\
+                       Blueprint 2:\n  Each ore robot costs 1 ore.\n";
+        assert_eq!(parse(Input::new(wrapped)).unwrap().len(), 2);
+    }
 }
@@ -20,8 +20,24 @@ fn mix<T>(v: &mut Vec<T>, from: usize, to: usize) {
 
 fn run(input: Input, mult: i64, rounds: i64) -> Result<i64> {
     let orig = parse(input, mult);
+    let mixed = mix_all(&orig, rounds);
+
+    let mut iter = mixed.iter().cycle();
+    for i in iter.by_ref() {
+        if *i == 0 {
+            break;
+        }
+    }
+    let i = iter.nth(999).unwrap();
+    let j = iter.nth(999).unwrap();
+    let k = iter.nth(999).unwrap();
+
+    Ok(i + j + k)
+}
+
+fn mix_all(orig: &[i64], rounds: i64) -> Vec<i64> {
     let mut pos = Vec::from_iter(0..orig.len());
-    let mut mixed = orig.clone();
+    let mut mixed = orig.to_vec();
     let len = orig.len() as i64;
 
     for _ in 0..rounds {
@@ -36,17 +52,7 @@ fn run(input: Input, mult: i64, rounds: i64) -> Result<i64> {
         }
     }
 
-    let mut iter = mixed.iter().cycle();
-    for i in iter.by_ref() {
-        if *i == 0 {
-            break;
-        }
-    }
-    let i = iter.nth(999).unwrap();
-    let j = iter.nth(999).unwrap();
-    let k = iter.nth(999).unwrap();
-
-    Ok(i + j + k)
+    mixed
 }
 
 fn parse(input: Input, mult: i64) -> Vec<i64> {
@@ -66,6 +72,7 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_part_one() {
@@ -78,4 +85,18 @@ mod tests {
         let input = advent_of_code::read_file("examples", 20);
         assert_eq!(part_two(Input::new(&input)).unwrap(), 1623178306);
     }
+
+    proptest! {
+        #[test]
+        fn prop_mix_keeps_values(
+            orig in prop::collection::vec(-10_000_i64..10_000, 2..200),
+            rounds in 1_i64..3,
+        ) {
+            let mut mixed = mix_all(&orig, rounds);
+            let mut orig = orig;
+            mixed.sort_unstable();
+            orig.sort_unstable();
+            prop_assert_eq!(mixed, orig);
+        }
+    }
 }
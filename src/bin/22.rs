@@ -1,4 +1,4 @@
-use advent_of_code::helpers::Input;
+use advent_of_code::helpers::{Input, Normalize};
 use anyhow::{anyhow, Result};
 use itertools::Itertools;
 
@@ -292,8 +292,14 @@ fn parse(input: Input) -> Result<(String, Vec<Vec<char>>)> {
     Ok((parts[1].trim_end().to_string(), grid))
 }
 
+/// the map keeps its leading spaces, but editors tend to pad or keep trailing ones,
+/// which would break the blank line between map and path.
+const NORMALIZE: Normalize = Normalize {
+    strip_trailing_whitespace: true,
+};
+
 fn main() {
-    let input = &advent_of_code::read_file("inputs", 22);
+    let input = &advent_of_code::read_file_with("inputs", 22, NORMALIZE);
     advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
     advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
 }
@@ -304,14 +310,14 @@ mod tests {
 
     #[test]
     fn test_part_one() {
-        let input = advent_of_code::read_file("examples", 22);
+        let input = advent_of_code::read_file_with("examples", 22, NORMALIZE);
         assert_eq!(part_one(Input::new(&input)).unwrap(), 6032);
     }
 
     // COMMENT IN TESTING DATA TO TEST PART TWO
     // #[test]
     // fn test_part_two() {
-    //     let input = advent_of_code::read_file("examples", 22);
+    //     let input = advent_of_code::read_file_with("examples", 22, NORMALIZE);
     //     assert_eq!(part_two(Input::new(&input)).unwrap(), 5031);
     // }
 }
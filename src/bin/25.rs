@@ -64,6 +64,8 @@ fn main() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use itertools::Itertools;
+    use proptest::prelude::*;
 
     #[test]
     fn test_part_one() {
@@ -76,4 +78,18 @@ mod tests {
         let input = advent_of_code::read_file("examples", 25);
         assert_eq!(part_two(Input::new(&input)).unwrap(), "Merry Christmas!");
     }
+
+    proptest! {
+        #[test]
+        fn prop_snafu_roundtrip(x in 0_i64..=i64::MAX / 5) {
+            prop_assert_eq!(snafu_to_base10(&base10_to_snafu(x).unwrap()).unwrap(), x);
+        }
+
+        #[test]
+        fn prop_snafu_sum(xs in prop::collection::vec(0_i64..1_000_000_000, 1..50)) {
+            let input = xs.iter().map(|x| base10_to_snafu(*x).unwrap()).join("\n");
+            let sum = part_one(Input::new(&input)).unwrap();
+            prop_assert_eq!(snafu_to_base10(&sum).unwrap(), xs.iter().sum::<i64>());
+        }
+    }
 }
@@ -0,0 +1,82 @@
+/*
+ * This file contains template code.
+ * There is no need to edit this file unless you want to change template functionality.
+ */
+use std::{
+    fs::OpenOptions,
+    io::{self, Write},
+    process,
+};
+
+use advent_of_code::generators;
+
+struct Args {
+    day: u8,
+    seed: u64,
+    out: Option<String>,
+    force: bool,
+}
+
+fn parse_args() -> Result<Args, pico_args::Error> {
+    let mut args = pico_args::Arguments::from_env();
+    Ok(Args {
+        force: args.contains(["-f", "--force"]),
+        seed: args.opt_value_from_str(["-s", "--seed"])?.unwrap_or(0),
+        out: args.opt_value_from_str(["-o", "--out"])?,
+        day: args.free_from_str()?,
+    })
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("Failed to process arguments: {}", e);
+            eprintln!("example: `cargo generate 12 --seed 42`");
+            process::exit(1);
+        }
+    };
+
+    let input = match generators::generate(args.day, args.seed) {
+        Some(input) => input,
+        None => {
+            eprintln!(
+                "No generator for day {}. Available days: {:?}",
+                args.day,
+                generators::DAYS
+            );
+            process::exit(1);
+        }
+    };
+
+    if args.out.as_deref() == Some("-") {
+        io::stdout()
+            .write_all(input.as_bytes())
+            .expect("could not write input to stdout.");
+        return;
+    }
+
+    let path = args
+        .out
+        .unwrap_or_else(|| format!("src/inputs/{:02}.txt", args.day));
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .create_new(!args.force)
+        .open(&path);
+
+    match file.and_then(|mut f| f.write_all(input.as_bytes())) {
+        Ok(_) => {
+            println!(
+                "🎄 Generated input for day {} (seed {}) in \"{}\".",
+                args.day, args.seed, &path
+            );
+        }
+        Err(e) => {
+            eprintln!("Failed to write input file \"{}\": {}", &path, e);
+            eprintln!("Use `--force` to overwrite an existing file.");
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,429 @@
+/*
+ * Seeded generators for synthetic puzzle inputs.
+ * Real inputs can't be shared, these produce valid inputs of realistic size for tests and benchmarks.
+ * The same day and seed always produce the same input.
+ */
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+
+use itertools::Itertools;
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Days that have a generator.
+pub const DAYS: [u8; 9] = [1, 4, 6, 12, 15, 16, 19, 20, 24];
+
+pub fn generate(day: u8, seed: u64) -> Option<String> {
+    let rng = &mut ChaCha8Rng::seed_from_u64(seed);
+    let input = match day {
+        1 => day01(rng, 250),
+        4 => day04(rng, 1000),
+        6 => day06(rng, 4096),
+        12 => day12(rng, 41, 161),
+        15 => day15(rng, 4_000_000),
+        16 => day16(rng, 58, 15),
+        19 => day19(rng, 30),
+        20 => day20(rng, 5000),
+        24 => day24(rng, 35, 100),
+        _ => return None,
+    };
+    Some(input)
+}
+
+pub fn rng(seed: u64) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(seed)
+}
+
+/// Calorie lists of `elves` elves.
+pub fn day01(rng: &mut impl Rng, elves: usize) -> String {
+    (0..elves)
+        .map(|_| {
+            let items = rng.gen_range(1..=15);
+            (0..items)
+                .map(|_| rng.gen_range(1000..=60000).to_string())
+                .join("\n")
+        })
+        .join("\n\n")
+        + "\n"
+}
+
+/// `pairs` section assignment pairs.
+pub fn day04(rng: &mut impl Rng, pairs: usize) -> String {
+    fn range(rng: &mut impl Rng) -> (u32, u32) {
+        let a = rng.gen_range(1..=99);
+        (a, rng.gen_range(a..=99))
+    }
+
+    (0..pairs)
+        .map(|_| {
+            let (a, b) = range(rng);
+            let (c, d) = range(rng);
+            format!("{a}-{b},{c}-{d}\n")
+        })
+        .collect()
+}
+
+/// A datastream of `len` bytes. The first start-of-message marker (14 distinct characters) is
+/// placed in the second half, the stream before it only uses 8 different characters.
+pub fn day06(rng: &mut impl Rng, len: usize) -> String {
+    let alphabet = (b'a'..=b'z').collect_vec();
+    let noise = alphabet.choose_multiple(rng, 8).copied().collect_vec();
+    let marker_at = rng.gen_range(len / 2..len - 14);
+
+    let mut stream = (0..marker_at)
+        .map(|_| *noise.choose(rng).unwrap())
+        .collect_vec();
+    stream.extend(alphabet.choose_multiple(rng, 14));
+    stream.extend((stream.len()..len).map(|_| *alphabet.choose(rng).unwrap()));
+
+    String::from_utf8(stream).unwrap() + "\n"
+}
+
+/// A `rows` x `cols` height map. `S` is in the first column and a climbable route to `E` is
+/// carved into the map first, the remaining cells are noise around the route's height.
+pub fn day12(rng: &mut impl Rng, rows: usize, cols: usize) -> String {
+    assert!(
+        cols >= 54,
+        "need at least 54 columns, the route ends in the right half and climbs from a to z"
+    );
+    let end_col = rng.gen_range(cols / 2..cols - 1);
+
+    // self-avoiding route: move vertically inside a column, then one step right.
+    let mut route = vec![];
+    let mut row = rng.gen_range(0..rows);
+    for col in 0..=end_col {
+        let target = rng.gen_range(0..rows);
+        while row != target {
+            route.push((row, col));
+            row = if target > row { row + 1 } else { row - 1 };
+        }
+        route.push((row, col));
+    }
+
+    let mut grid = (0..rows)
+        .map(|_| {
+            (0..cols)
+                .map(|col| {
+                    let base = (25 * col / end_col).min(25) as i32;
+                    (base + rng.gen_range(-6..=2)).clamp(0, 25) as u8
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+
+    let len = route.len();
+    for (k, &(i, j)) in route.iter().enumerate() {
+        grid[i][j] = (25 * k / (len - 1)) as u8;
+    }
+
+    let (si, sj) = route[0];
+    let (ei, ej) = route[len - 1];
+    grid.iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(j, h)| match (i, j) {
+                    p if p == (si, sj) => 'S',
+                    p if p == (ei, ej) => 'E',
+                    _ => (b'a' + h) as char,
+                })
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
+
+/// A sensor field that leaves exactly one cell uncovered inside `0..=max_dim`.
+///
+/// Every sensor's range stops one short of the hidden cell. Eight sensors around the hidden
+/// cell cover its neighbourhood, a jittered 5x5 lattice covers the rest of the area.
+pub fn day15(rng: &mut impl Rng, max_dim: i64) -> String {
+    let dist = |(x1, y1): (i64, i64), (x2, y2): (i64, i64)| (x1 - x2).abs() + (y1 - y2).abs();
+
+    let margin = max_dim / 10;
+    let hidden = (
+        rng.gen_range(margin..=max_dim - margin),
+        rng.gen_range(margin..=max_dim - margin),
+    );
+    let spacing = max_dim / 4;
+    let jitter = spacing / 8;
+    // furthest any cell can be from its closest lattice sensor.
+    let reach = spacing + 2 * jitter;
+    let c = 2 * reach + 1;
+
+    let mut sensors = vec![];
+    for (dx, dy) in [
+        (c, 0),
+        (-c, 0),
+        (0, c),
+        (0, -c),
+        (c, c),
+        (c, -c),
+        (-c, c),
+        (-c, -c),
+    ] {
+        let s = (hidden.0 + dx, hidden.1 + dy);
+        sensors.push((s, dist(s, hidden) - 1));
+    }
+    for (a, b) in (0..=4).cartesian_product(0..=4) {
+        let s = (
+            a * spacing + rng.gen_range(-jitter..=jitter),
+            b * spacing + rng.gen_range(-jitter..=jitter),
+        );
+        let d = dist(s, hidden);
+        if d > 1 {
+            sensors.push((s, rng.gen_range(reach..=2 * reach).min(d - 1)));
+        }
+    }
+    sensors.shuffle(rng);
+
+    // prefer a beacon that is not inside another sensor's range, so it is the closest one.
+    let on_edge = |(x, y): (i64, i64), r: i64, t: i64| {
+        let o = t % r;
+        match t / r {
+            0 => (x + r - o, y + o),
+            1 => (x - o, y + r - o),
+            2 => (x - r + o, y - o),
+            _ => (x + o, y - r + o),
+        }
+    };
+    let mut out = String::new();
+    for &(s, r) in &sensors {
+        let candidates = (0..4)
+            .map(|side| side * r)
+            .chain((0..16).map(|_| rng.gen_range(0..4 * r)))
+            .map(|t| on_edge(s, r, t))
+            .collect_vec();
+        let beacon = candidates
+            .iter()
+            .find(|&&b| sensors.iter().all(|&(o, or)| o == s || dist(o, b) > or))
+            .unwrap_or(&candidates[0]);
+        writeln!(
+            out,
+            "Sensor at x={}, y={}: closest beacon is at x={}, y={}",
+            s.0, s.1, beacon.0, beacon.1
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// A tunnel network of `valves` valves, `flowing` of which have a positive flow rate. Valves
+/// with a flow rate are connected by corridors of zero-rate valves.
+pub fn day16(rng: &mut impl Rng, valves: usize, flowing: usize) -> String {
+    let mut names = (b'A'..=b'Z')
+        .cartesian_product(b'A'..=b'Z')
+        .filter(|&(a, b)| (a, b) != (b'A', b'A'))
+        .map(|(a, b)| String::from_utf8(vec![a, b]).unwrap())
+        .collect_vec();
+    names.shuffle(rng);
+    names.insert(0, "AA".to_string());
+    names.truncate(valves.max(flowing + 1));
+
+    let mut rates = vec![0; names.len()];
+    for rate in rates.iter_mut().skip(1).take(flowing) {
+        *rate = rng.gen_range(3..=25);
+    }
+
+    let mut tunnels = vec![vec![]; names.len()];
+    let mut connect = |a: usize, b: usize| {
+        if a != b && !tunnels[a].contains(&b) {
+            tunnels[a].push(b);
+            tunnels[b].push(a);
+        }
+    };
+
+    // rooms (AA and flowing valves) form a random tree plus some extra links, every link
+    // is a corridor through the remaining zero-rate valves.
+    let rooms = flowing + 1;
+    let mut links = (1..rooms)
+        .map(|room| (rng.gen_range(0..room), room))
+        .collect_vec();
+    for _ in 0..rooms / 3 {
+        links.push((rng.gen_range(0..rooms), rng.gen_range(0..rooms)));
+    }
+    let mut corridors = vec![vec![]; links.len()];
+    for v in rooms..names.len() {
+        corridors[rng.gen_range(0..links.len())].push(v);
+    }
+    for ((a, b), corridor) in links.into_iter().zip(corridors) {
+        let mut prev = a;
+        for v in corridor {
+            connect(prev, v);
+            prev = v;
+        }
+        connect(prev, b);
+    }
+
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            let others = tunnels[i].iter().map(|&t| &names[t]).join(", ");
+            if tunnels[i].len() == 1 {
+                format!(
+                    "Valve {} has flow rate={}; tunnel leads to valve {}\n",
+                    name, rates[i], others
+                )
+            } else {
+                format!(
+                    "Valve {} has flow rate={}; tunnels lead to valves {}\n",
+                    name, rates[i], others
+                )
+            }
+        })
+        .collect()
+}
+
+/// `count` robot blueprints with costs in the ranges seen in real inputs.
+pub fn day19(rng: &mut impl Rng, count: u32) -> String {
+    (1..=count)
+        .map(|id| {
+            format!(
+                "Blueprint {}: Each ore robot costs {} ore. Each clay robot costs {} ore. Each obsidian robot costs {} ore and {} clay. Each geode robot costs {} ore and {} obsidian.\n",
+                id,
+                rng.gen_range(2..=4),
+                rng.gen_range(2..=4),
+                rng.gen_range(2..=4),
+                rng.gen_range(5..=20),
+                rng.gen_range(2..=4),
+                rng.gen_range(7..=20),
+            )
+        })
+        .collect()
+}
+
+/// `len` encrypted numbers containing exactly one zero.
+pub fn day20(rng: &mut impl Rng, len: usize) -> String {
+    let mut nums = (1..len)
+        .map(|_| {
+            let x = rng.gen_range(1..=10_000);
+            if rng.gen_bool(0.5) {
+                x
+            } else {
+                -x
+            }
+        })
+        .collect_vec();
+    nums.push(0);
+    nums.shuffle(rng);
+    nums.iter().map(|x| format!("{x}\n")).collect()
+}
+
+/// A blizzard valley with a `rows` x `cols` inner area. The entrance and exit columns never
+/// contain vertical blizzards and the valley is only returned if there and back again is possible.
+pub fn day24(rng: &mut impl Rng, rows: usize, cols: usize) -> String {
+    loop {
+        let inner = (0..rows)
+            .map(|_| {
+                (0..cols)
+                    .map(|j| {
+                        let vertical = j != 0 && j != cols - 1;
+                        match rng.gen_range(0..10) {
+                            0..=2 => '.',
+                            3..=4 => '>',
+                            5..=6 => '<',
+                            7 if vertical => '^',
+                            8 if vertical => 'v',
+                            _ => '.',
+                        }
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+
+        if valley_crossable(&inner) {
+            let wall = |gap: usize| {
+                (0..cols + 2)
+                    .map(|j| if j == gap { '.' } else { '#' })
+                    .collect::<String>()
+            };
+            let mut out = wall(1) + "\n";
+            for row in &inner {
+                writeln!(out, "#{}#", row.iter().collect::<String>()).unwrap();
+            }
+            out += &wall(cols);
+            out.push('\n');
+            return out;
+        }
+    }
+}
+
+fn valley_crossable(inner: &[Vec<char>]) -> bool {
+    let (rows, cols) = (inner.len() as i64, inner[0].len() as i64);
+    let free = |t: i64, i: i64, j: i64| {
+        inner[i as usize][(j - t).rem_euclid(cols) as usize] != '>'
+            && inner[i as usize][(j + t).rem_euclid(cols) as usize] != '<'
+            && inner[(i - t).rem_euclid(rows) as usize][j as usize] != 'v'
+            && inner[(i + t).rem_euclid(rows) as usize][j as usize] != '^'
+    };
+
+    // positions are in inner coordinates, the entrance is (-1, 0) and the exit (rows, cols - 1).
+    let goals = [(rows, cols - 1), (-1, 0), (rows, cols - 1)];
+    let period = (1..=rows * cols)
+        .find(|p| p % rows == 0 && p % cols == 0)
+        .unwrap();
+    let mut seen = HashSet::new();
+    let mut q = VecDeque::from([(0_i64, (-1_i64, 0_i64), 0_usize)]);
+    while let Some((t, (i, j), mut stage)) = q.pop_front() {
+        if (i, j) == goals[stage] {
+            stage += 1;
+            if stage == goals.len() {
+                return true;
+            }
+        }
+        if !seen.insert((t % period, (i, j), stage)) {
+            continue;
+        }
+        for (di, dj) in [(0, 0), (0, 1), (0, -1), (1, 0), (-1, 0)] {
+            let (ni, nj) = (i + di, j + dj);
+            let endpoint = (ni, nj) == (-1, 0) || (ni, nj) == (rows, cols - 1);
+            let inside = (0..rows).contains(&ni) && (0..cols).contains(&nj);
+            if endpoint || (inside && free(t + 1, ni, nj)) {
+                q.push_back((t + 1, (ni, nj), stage));
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reproducible() {
+        for day in DAYS {
+            assert_eq!(generate(day, 7), generate(day, 7));
+        }
+        assert_ne!(generate(12, 1), generate(12, 2));
+    }
+
+    #[test]
+    fn test_day15_single_uncovered() {
+        for seed in 0..10 {
+            let input = day15(&mut rng(seed), 40);
+            let sensors = input
+                .lines()
+                .map(|l| {
+                    let n = l
+                        .split(|c: char| c != '-' && !c.is_ascii_digit())
+                        .filter(|s| !s.is_empty())
+                        .map(|s| s.parse::<i64>().unwrap())
+                        .collect_vec();
+                    ((n[0], n[1]), (n[0] - n[2]).abs() + (n[1] - n[3]).abs())
+                })
+                .collect_vec();
+            let uncovered = (0..=40)
+                .cartesian_product(0..=40)
+                .filter(|&(x, y)| {
+                    sensors
+                        .iter()
+                        .all(|&((sx, sy), r)| (sx - x).abs() + (sy - y).abs() > r)
+                })
+                .count();
+            assert_eq!(uncovered, 1);
+        }
+    }
+}
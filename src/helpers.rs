@@ -1,5 +1,10 @@
+use std::{cmp::Ordering, fmt};
+
 use itertools::Itertools;
 
+pub mod interval;
+pub mod ocr;
+
 pub struct Input<'a>(&'a str);
 
 impl<'a> Input<'a> {
@@ -36,3 +41,227 @@ impl<'a> Input<'a> {
         self.0.split("\n\n").map(move |g| g.lines().map(f))
     }
 }
+
+/// Keeps the `k` largest items pushed into it according to `cmp`, in `O(k)` memory.
+///
+/// Items live in a binary heap whose root is the smallest kept item, so a new item only has to
+/// be compared against the root to know if it makes the cut.
+pub struct TopK<T, F> {
+    k: usize,
+    heap: Vec<T>,
+    cmp: F,
+}
+
+impl<T: Ord> TopK<T, fn(&T, &T) -> Ordering> {
+    pub fn new(k: usize) -> Self {
+        Self::by(k, T::cmp)
+    }
+}
+
+impl<T, F> TopK<T, F>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    pub fn by(k: usize, cmp: F) -> Self {
+        Self {
+            k,
+            heap: Vec::with_capacity(k),
+            cmp,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn push(&mut self, item: T) {
+        if self.heap.len() < self.k {
+            self.heap.push(item);
+            self.sift_up(self.heap.len() - 1);
+        } else if self.k > 0 && (self.cmp)(&item, &self.heap[0]) == Ordering::Greater {
+            self.heap[0] = item;
+            self.sift_down(0);
+        }
+    }
+
+    /// Returns the kept items, largest first.
+    pub fn into_sorted_vec(self) -> Vec<T> {
+        let cmp = self.cmp;
+        let mut v = self.heap;
+        v.sort_by(|a, b| cmp(b, a));
+        v
+    }
+
+    fn less(&self, i: usize, j: usize) -> bool {
+        (self.cmp)(&self.heap[i], &self.heap[j]) == Ordering::Less
+    }
+
+    fn sift_up(&mut self, mut i: usize) {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if !self.less(i, parent) {
+                break;
+            }
+            self.heap.swap(i, parent);
+            i = parent;
+        }
+    }
+
+    fn sift_down(&mut self, mut i: usize) {
+        loop {
+            let mut smallest = i;
+            for child in [2 * i + 1, 2 * i + 2] {
+                if child < self.heap.len() && self.less(child, smallest) {
+                    smallest = child;
+                }
+            }
+            if smallest == i {
+                break;
+            }
+            self.heap.swap(i, smallest);
+            i = smallest;
+        }
+    }
+}
+
+impl<T, F> Extend<T> for TopK<T, F>
+where
+    F: Fn(&T, &T) -> Ordering,
+{
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|item| self.push(item));
+    }
+}
+
+/// Options for [`normalize`]. Line endings are always normalized to `\n`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Normalize {
+    /// strip whitespace from the end of every line.
+    pub strip_trailing_whitespace: bool,
+}
+
+/// Converts `\r\n` and lone `\r` line endings to `\n` and optionally strips
+/// trailing whitespace from every line. A trailing newline is preserved.
+pub fn normalize(s: &str, opts: Normalize) -> String {
+    let s = s.replace("\r\n", "\n").replace('\r', "\n");
+    if !opts.strip_trailing_whitespace {
+        return s;
+    }
+
+    let mut ret = s.lines().map(str::trim_end).join("\n");
+    if s.ends_with('\n') {
+        ret.push('\n');
+    }
+    ret
+}
+
+/// Common mistakes in puzzle input files, detected by [`check_input`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum InputError {
+    Empty,
+    Html,
+    Truncated { last_line: String },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Empty => write!(
+                f,
+                "file is empty. paste the puzzle input or run `cargo download <day>`"
+            ),
+            InputError::Html => write!(
+                f,
+                "file contains an HTML page instead of a puzzle input. is the session cookie expired?"
+            ),
+            InputError::Truncated { last_line } => write!(
+                f,
+                "file does not end with a newline, the download may be truncated. last line: {:?}",
+                last_line
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Checks a normalized input for common mistakes. Set `complete` to also
+/// require the trailing newline every downloaded puzzle input ends with.
+pub fn check_input(s: &str, complete: bool) -> Result<(), InputError> {
+    if s.trim().is_empty() {
+        return Err(InputError::Empty);
+    }
+
+    let start = s.trim_start().get(..64).unwrap_or(s.trim_start());
+    let start = start.to_ascii_lowercase();
+    if start.starts_with("<!doctype html")
+        || start.starts_with("<html")
+        || s.contains("Puzzle inputs differ by user.  Please log in")
+    {
+        return Err(InputError::Html);
+    }
+
+    if complete && !s.ends_with('\n') {
+        return Err(InputError::Truncated {
+            last_line: s.lines().last().unwrap_or_default().to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_top_k() {
+        let mut top = TopK::new(3);
+        top.extend([5, 1, 9, 3, 7, 9, 2]);
+        assert_eq!(top.into_sorted_vec(), vec![9, 9, 7]);
+
+        let mut bottom = TopK::by(2, |a: &i32, b: &i32| b.cmp(a));
+        bottom.extend([5, 1, 9, 3]);
+        assert_eq!(bottom.into_sorted_vec(), vec![1, 3]);
+
+        let mut none = TopK::new(0);
+        none.push(1);
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            normalize("a\r\n\r\nb\r\n", Normalize::default()),
+            "a\n\nb\n"
+        );
+        assert_eq!(
+            normalize(
+                "a  \r\n b\t\n",
+                Normalize {
+                    strip_trailing_whitespace: true
+                }
+            ),
+            "a\n b\n"
+        );
+    }
+
+    #[test]
+    fn test_check_input() {
+        assert_eq!(check_input("", false), Err(InputError::Empty));
+        assert_eq!(
+            check_input("<!DOCTYPE html>\n<html lang=\"en-us\">", false),
+            Err(InputError::Html)
+        );
+        assert!(matches!(
+            check_input("1\n2\n3", true),
+            Err(InputError::Truncated { .. })
+        ));
+        assert_eq!(check_input("1\n2\n3", false), Ok(()));
+        assert_eq!(check_input("1\n2\n3\n", true), Ok(()));
+    }
+}
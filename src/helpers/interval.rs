@@ -0,0 +1,268 @@
+use std::fmt;
+use std::ops::{Add, Sub};
+
+/// Integer types intervals can be built from.
+pub trait Bound: Copy + Ord + Add<Output = Self> + Sub<Output = Self> + fmt::Debug {
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_bound {
+    ($($t:ty),*) => {
+        $(impl Bound for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+        })*
+    };
+}
+
+impl_bound!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// A contiguous range of integers, stored half-open as `start..end`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Bound> Interval<T> {
+    /// `start..end`, empty if `end <= start`.
+    pub fn exclusive(start: T, end: T) -> Self {
+        Self {
+            start,
+            end: end.max(start),
+        }
+    }
+
+    /// `start..=end`, empty if `end < start`. Panics if `end` is the maximum of `T`, see
+    /// [`Interval::try_inclusive`].
+    pub fn inclusive(start: T, end: T) -> Self {
+        Self::try_inclusive(start, end)
+            .unwrap_or_else(|| panic!("interval can't include the maximum value {:?}", end))
+    }
+
+    /// `start..=end`, empty if `end < start`. `None` if `end` is the maximum of `T`, since the
+    /// half-open interval would have to end after it.
+    pub fn try_inclusive(start: T, end: T) -> Option<Self> {
+        if end < start {
+            Some(Self::exclusive(start, start))
+        } else {
+            Some(Self::exclusive(start, end.checked_add(T::ONE)?))
+        }
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    /// First value after the interval.
+    pub fn end(&self) -> T {
+        self.end
+    }
+
+    /// Last value in the interval, `None` if it is empty.
+    pub fn last(&self) -> Option<T> {
+        (!self.is_empty()).then(|| self.end - T::ONE)
+    }
+
+    pub fn len(&self) -> T {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    pub fn contains(&self, x: T) -> bool {
+        self.start <= x && x < self.end
+    }
+
+    /// Whether `other` lies completely inside `self`. The empty interval is inside every interval.
+    pub fn contains_interval(&self, other: &Self) -> bool {
+        other.is_empty() || (self.start <= other.start && other.end <= self.end)
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        !self.intersection(other).is_empty()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self::exclusive(self.start.max(other.start), self.end.min(other.end))
+    }
+}
+
+impl<T: Bound> fmt::Debug for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}..{:?}", self.start, self.end)
+    }
+}
+
+/// A set of integers stored as sorted, disjoint and non-adjacent intervals.
+#[derive(Clone, PartialEq, Eq, Default)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: Bound> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self { intervals: vec![] }
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        if interval.is_empty() {
+            return;
+        }
+
+        // all intervals overlapping or touching the new one are merged into it.
+        let first = self.intervals.partition_point(|i| i.end < interval.start);
+        let last = self.intervals.partition_point(|i| i.start <= interval.end);
+        let merged = self.intervals[first..last]
+            .iter()
+            .fold(interval, |acc, i| Interval {
+                start: acc.start.min(i.start),
+                end: acc.end.max(i.end),
+            });
+        self.intervals.splice(first..last, [merged]);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval<T>> {
+        self.intervals.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn contains(&self, x: T) -> bool {
+        let i = self.intervals.partition_point(|i| i.end <= x);
+        self.intervals.get(i).is_some_and(|i| i.contains(x))
+    }
+
+    /// Number of integers in the set.
+    pub fn len(&self) -> T {
+        self.intervals.iter().fold(T::ZERO, |sum, i| sum + i.len())
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut ret = self.clone();
+        other.iter().for_each(|i| ret.insert(*i));
+        ret
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut ret = Self::new();
+        let (mut a, mut b) = (
+            self.intervals.iter().peekable(),
+            other.intervals.iter().peekable(),
+        );
+        while let (Some(x), Some(y)) = (a.peek(), b.peek()) {
+            ret.insert(x.intersection(y));
+            if x.end < y.end {
+                a.next();
+            } else {
+                b.next();
+            }
+        }
+        ret
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        match (self.intervals.first(), self.intervals.last()) {
+            (Some(first), Some(last)) => {
+                let bounds = Interval::exclusive(first.start, last.end);
+                self.intersection(&other.complement_in(bounds))
+            }
+            _ => Self::new(),
+        }
+    }
+
+    /// Everything in `bounds` that is not in the set.
+    pub fn complement_in(&self, bounds: Interval<T>) -> Self {
+        let mut ret = Self::new();
+        let mut start = bounds.start;
+        for i in &self.intervals {
+            ret.insert(Interval::exclusive(start, i.start.min(bounds.end)));
+            start = start.max(i.end);
+        }
+        ret.insert(Interval::exclusive(start, bounds.end));
+        ret
+    }
+
+    /// The holes between consecutive intervals of the set.
+    pub fn gaps(&self) -> impl Iterator<Item = Interval<T>> + '_ {
+        self.intervals
+            .windows(2)
+            .map(|w| Interval::exclusive(w[0].end, w[1].start))
+    }
+}
+
+impl<T: Bound> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut ret = Self::new();
+        iter.into_iter().for_each(|i| ret.insert(i));
+        ret
+    }
+}
+
+impl<T: Bound> fmt::Debug for IntervalSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.intervals.iter()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(intervals: &[(i32, i32)]) -> IntervalSet<i32> {
+        intervals
+            .iter()
+            .map(|&(a, b)| Interval::inclusive(a, b))
+            .collect()
+    }
+
+    #[test]
+    fn test_interval() {
+        let a = Interval::inclusive(2, 8);
+        let b = Interval::exclusive(3, 8);
+        assert_eq!(a.len(), 7);
+        assert_eq!(a.last(), Some(8));
+        assert!(a.contains_interval(&b) && !b.contains_interval(&a));
+        assert!(a.overlaps(&Interval::inclusive(8, 9)));
+        assert!(!b.overlaps(&Interval::inclusive(8, 9)));
+        assert!(Interval::inclusive(5, 4).is_empty());
+        assert_eq!(
+            Interval::try_inclusive(0, u8::MAX - 1).map(|i| i.len()),
+            Some(u8::MAX)
+        );
+        assert_eq!(Interval::try_inclusive(0, u8::MAX), None);
+        assert!(Interval::try_inclusive(u8::MAX, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_interval_set() {
+        let a = set(&[(0, 3), (10, 12), (4, 5), (20, 25)]);
+        assert_eq!(a, set(&[(0, 5), (10, 12), (20, 25)]));
+        assert_eq!(a.len(), 15);
+        assert!(a.contains(12) && !a.contains(13));
+        assert_eq!(
+            a.gaps().collect::<Vec<_>>(),
+            vec![Interval::inclusive(6, 9), Interval::inclusive(13, 19)]
+        );
+
+        let b = set(&[(2, 11), (24, 30)]);
+        assert_eq!(a.union(&b), set(&[(0, 12), (20, 30)]));
+        assert_eq!(a.intersection(&b), set(&[(2, 5), (10, 11), (24, 25)]));
+        assert_eq!(a.difference(&b), set(&[(0, 1), (12, 12), (20, 23)]));
+        assert_eq!(
+            a.complement_in(Interval::inclusive(-5, 21)),
+            set(&[(-5, -1), (6, 9), (13, 19)])
+        );
+    }
+}
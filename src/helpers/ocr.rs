@@ -0,0 +1,124 @@
+use std::fmt;
+
+/// Rows of a letter in the Advent of Code font.
+pub const LETTER_HEIGHT: usize = 6;
+/// Columns of a letter, plus the blank column separating it from the next one.
+pub const LETTER_STRIDE: usize = 5;
+const LETTER_WIDTH: usize = 4;
+
+/// Every letter the 4×6 font is known to contain.
+const GLYPHS: &[(char, [&str; LETTER_HEIGHT])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum OcrError {
+    /// the picture is not exactly one line of letters high.
+    Height(usize),
+    /// the letter at `index` (counting from 0) is not in the font.
+    UnknownGlyph { index: usize, glyph: String },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OcrError::Height(h) => write!(
+                f,
+                "picture is {} pixels high, letters are {}",
+                h, LETTER_HEIGHT
+            ),
+            OcrError::UnknownGlyph { index, glyph } => {
+                write!(f, "letter {} is not in the font:\n{}", index + 1, glyph)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// Reads the letters drawn into a row-major picture `width` pixels wide, one letter every
+/// [`LETTER_STRIDE`] columns.
+pub fn read_letters(pixels: &[bool], width: usize) -> Result<String, OcrError> {
+    let height = pixels.len().checked_div(width).unwrap_or(0);
+    if height != LETTER_HEIGHT {
+        return Err(OcrError::Height(height));
+    }
+
+    (0..(width + 1) / LETTER_STRIDE)
+        .map(|index| {
+            let glyph = (0..LETTER_HEIGHT)
+                .map(|row| {
+                    let start = row * width + index * LETTER_STRIDE;
+                    pixels[start..start + LETTER_WIDTH]
+                        .iter()
+                        .map(|&lit| if lit { '#' } else { '.' })
+                        .collect::<String>()
+                })
+                .collect::<Vec<_>>();
+            GLYPHS
+                .iter()
+                .find(|(_, rows)| rows[..] == glyph[..])
+                .map(|(c, _)| *c)
+                .ok_or_else(|| OcrError::UnknownGlyph {
+                    index,
+                    glyph: glyph.join("\n"),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picture(letters: &[char]) -> (Vec<bool>, usize) {
+        let width = letters.len() * LETTER_STRIDE;
+        let mut pixels = vec![false; width * LETTER_HEIGHT];
+        for (i, l) in letters.iter().enumerate() {
+            let (_, rows) = GLYPHS.iter().find(|(c, _)| c == l).unwrap();
+            for (r, row) in rows.iter().enumerate() {
+                for (c, p) in row.chars().enumerate() {
+                    pixels[r * width + i * LETTER_STRIDE + c] = p == '#';
+                }
+            }
+        }
+        (pixels, width)
+    }
+
+    #[test]
+    fn test_read_letters() {
+        let all = GLYPHS.iter().map(|(c, _)| *c).collect::<Vec<_>>();
+        let (pixels, width) = picture(&all);
+        assert_eq!(
+            read_letters(&pixels, width).unwrap(),
+            all.iter().collect::<String>()
+        );
+
+        let (mut pixels, width) = picture(&['A', 'B']);
+        pixels[LETTER_STRIDE + 3] = true;
+        assert!(matches!(
+            read_letters(&pixels, width),
+            Err(OcrError::UnknownGlyph { index: 1, .. })
+        ));
+        assert_eq!(
+            read_letters(&pixels[..width], width),
+            Err(OcrError::Height(1))
+        );
+    }
+}
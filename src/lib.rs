@@ -4,10 +4,21 @@
  * Prefer `./helpers.rs` if you want to extract code from your solutions.
  */
 use std::env;
+use std::fmt::Display;
 use std::fs;
+use std::process;
 
+use anyhow::Context;
+use helpers::{check_input, normalize, Input, InputError, Normalize};
+
+pub mod alloc_stats;
+pub mod generators;
 pub mod helpers;
 
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static GLOBAL: alloc_stats::CountingAllocator = alloc_stats::CountingAllocator;
+
 pub const ANSI_ITALIC: &str = "\x1b[3m";
 pub const ANSI_BOLD: &str = "\x1b[1m";
 pub const ANSI_RESET: &str = "\x1b[0m";
@@ -15,19 +26,22 @@ pub const ANSI_RESET: &str = "\x1b[0m";
 #[macro_export]
 macro_rules! solve {
     ($part:expr, $solver:ident, $input:expr) => {{
-        use advent_of_code::{ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
+        use advent_of_code::{alloc_stats, ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
         use std::fmt::Display;
         use std::time::Instant;
 
         fn print_result<T: Display>(func: impl FnOnce(Input) -> anyhow::Result<T>, input: Input) {
+            alloc_stats::reset();
             let timer = Instant::now();
             let result = func(input);
             let elapsed = timer.elapsed();
+            let stats = alloc_stats::snapshot();
             match result {
                 Ok(result) => {
+                    let stats = stats.map(|s| format!(" ({})", s)).unwrap_or_default();
                     println!(
-                        "{} {}(elapsed: {:.2?}){}",
-                        result, ANSI_ITALIC, elapsed, ANSI_RESET
+                        "{} {}(elapsed: {:.2?}){}{}",
+                        result, ANSI_ITALIC, elapsed, stats, ANSI_RESET
                     );
                 }
                 Err(e) => {
@@ -41,13 +55,185 @@ macro_rules! solve {
     }};
 }
 
+/// Runs a day whose parts have several named implementations. The first implementation of each
+/// part is the default. Pass `--impl <name>` to select another one, or `--diff` to run all of
+/// them on the example, the real input and generated inputs and report disagreements.
+///
+/// ```ignore
+/// advent_of_code::solve_impls!(6, [array: part_one, map: part_one_map], [array: part_two]);
+/// ```
+#[macro_export]
+macro_rules! solve_impls {
+    ($day:expr, [$($name1:ident: $solver1:path),+ $(,)?], [$($name2:ident: $solver2:path),+ $(,)?]) => {{
+        use advent_of_code::Solver;
+
+        let part_one_impls: &[(&str, Solver<_>)] = &[$((stringify!($name1), $solver1 as Solver<_>)),+];
+        let part_two_impls: &[(&str, Solver<_>)] = &[$((stringify!($name2), $solver2 as Solver<_>)),+];
+
+        let args = advent_of_code::ImplArgs::from_env();
+        if args.diff {
+            let agree = advent_of_code::differential($day, part_one_impls, part_two_impls);
+            std::process::exit(if agree { 0 } else { 1 });
+        }
+
+        let (part_one, part_two) =
+            advent_of_code::select_impls(part_one_impls, part_two_impls, args.name.as_deref());
+        let input = &advent_of_code::read_file("inputs", $day);
+        advent_of_code::solve!(1, part_one, Input::new(input.as_str()));
+        advent_of_code::solve!(2, part_two, Input::new(input.as_str()));
+    }};
+}
+
+/// A solver for one part of a day.
+pub type Solver<T> = fn(Input) -> anyhow::Result<T>;
+
+pub struct ImplArgs {
+    pub name: Option<String>,
+    pub diff: bool,
+}
+
+impl ImplArgs {
+    pub fn from_env() -> Self {
+        let mut args = pico_args::Arguments::from_env();
+        Self {
+            diff: args.contains("--diff"),
+            name: args.opt_value_from_str("--impl").unwrap_or_else(|e| {
+                eprintln!("Failed to process arguments: {}", e);
+                process::exit(1);
+            }),
+        }
+    }
+}
+
+/// Picks the implementation called `name` for both parts. A part without an implementation of
+/// that name falls back to its default.
+pub fn select_impls<A, B>(
+    part_one: &[(&str, Solver<A>)],
+    part_two: &[(&str, Solver<B>)],
+    name: Option<&str>,
+) -> (Solver<A>, Solver<B>) {
+    fn find<T>(impls: &[(&str, Solver<T>)], name: Option<&str>) -> Option<Solver<T>> {
+        impls
+            .iter()
+            .find(|(n, _)| Some(*n) == name)
+            .map(|(_, solver)| *solver)
+    }
+
+    let one = find(part_one, name);
+    let two = find(part_two, name);
+    if let (Some(name), None, None) = (name, one, two) {
+        let mut names = part_one.iter().map(|(n, _)| *n).collect::<Vec<_>>();
+        for (n, _) in part_two {
+            if !names.contains(n) {
+                names.push(n);
+            }
+        }
+        eprintln!(
+            "Unknown implementation \"{}\". Available: {}",
+            name,
+            names.join(", ")
+        );
+        process::exit(1);
+    }
+    (one.unwrap_or(part_one[0].1), two.unwrap_or(part_two[0].1))
+}
+
+/// Runs every implementation of both parts on the example, the real input (if present) and
+/// generated inputs (if the day has a generator). Returns `false` if any implementations disagree.
+pub fn differential<A: Display, B: Display>(
+    day: u8,
+    part_one: &[(&str, Solver<A>)],
+    part_two: &[(&str, Solver<B>)],
+) -> bool {
+    let mut sources = vec![];
+    for folder in ["examples", "inputs"] {
+        match try_read_file(folder, day, Normalize::default()) {
+            Ok(input) => sources.push((folder.to_string(), input)),
+            Err(e) => println!("skipping {}: {:#}", folder, e),
+        }
+    }
+    for seed in 0..DIFF_SEEDS {
+        if let Some(input) = generators::generate(day, seed) {
+            sources.push((format!("generated (seed {})", seed), input));
+        }
+    }
+
+    let mut agree = true;
+    for (source, input) in &sources {
+        agree &= compare_impls(1, source, part_one, input);
+        agree &= compare_impls(2, source, part_two, input);
+    }
+    agree
+}
+
+const DIFF_SEEDS: u64 = 3;
+
+fn compare_impls<T: Display>(
+    part: u8,
+    source: &str,
+    impls: &[(&str, Solver<T>)],
+    input: &str,
+) -> bool {
+    let results = impls
+        .iter()
+        .map(|(name, solver)| {
+            let result = match solver(Input::new(input)) {
+                Ok(result) => result.to_string(),
+                Err(e) => format!("error: {}", e),
+            };
+            (name, result)
+        })
+        .collect::<Vec<_>>();
+
+    let expected = &results[0].1;
+    if results.iter().all(|(_, r)| r == expected) {
+        println!(
+            "✔ part {} {}: {} implementation(s) agree on {}",
+            part,
+            source,
+            results.len(),
+            expected
+        );
+        true
+    } else {
+        println!("✘ part {} {}: implementations disagree", part, source);
+        for (name, result) in &results {
+            println!("    {}: {}", name, result);
+        }
+        false
+    }
+}
+
 pub fn read_file(folder: &str, day: u8) -> String {
-    let cwd = env::current_dir().unwrap();
+    read_file_with(folder, day, Normalize::default())
+}
+
+/// Reads and normalizes an input file, panicking with a descriptive message if the
+/// file is missing, empty or obviously not a puzzle input. A possibly truncated
+/// input only prints a warning.
+pub fn read_file_with(folder: &str, day: u8, opts: Normalize) -> String {
+    match try_read_file(folder, day, opts) {
+        Ok(s) => s,
+        Err(e) => panic!("{:#}", e),
+    }
+}
+
+pub fn try_read_file(folder: &str, day: u8, opts: Normalize) -> anyhow::Result<String> {
+    let cwd = env::current_dir()?;
 
     let filepath = cwd.join("src").join(folder).join(format!("{:02}.txt", day));
 
-    let f = fs::read_to_string(filepath);
-    f.expect("could not open input file")
+    let f = fs::read_to_string(&filepath)
+        .with_context(|| format!("could not open input file {:?}", filepath))?;
+    let s = normalize(&f, opts);
+    // only downloaded inputs are guaranteed to end with a newline, and a hand-pasted
+    // input may still be complete without one.
+    match check_input(&s, folder == "inputs") {
+        Err(e @ InputError::Truncated { .. }) => eprintln!("warning: {:?}: {}", filepath, e),
+        r => r.with_context(|| format!("invalid input file {:?}", filepath))?,
+    }
+
+    Ok(s)
 }
 
 fn parse_time(val: &str, postfix: &str) -> f64 {
@@ -115,6 +301,13 @@ mod tests {
             1.52_f64
         );
 
+        assert_approx_eq!(
+            parse_exec_time(
+                "🎄 Part 1 🎄\n0 (elapsed: 70µs) (peak: 1.50KiB, allocs: 3, allocated: 2.00KiB)\n🎄 Part 2 🎄\n0 (elapsed: 1.45ms) (peak: 0B, allocs: 0, allocated: 0B)"
+            ),
+            1.52_f64
+        );
+
         assert_approx_eq!(
             parse_exec_time(
                 "🎄 Part 1 🎄\n0 (elapsed: 10.3s)\n🎄 Part 2 🎄\n0 (elapsed: 100.50ms)"
@@ -5,15 +5,30 @@
 use advent_of_code::{ANSI_BOLD, ANSI_ITALIC, ANSI_RESET};
 use std::process::Command;
 
+struct Args {
+    alloc: bool,
+}
+
+fn parse_args() -> Args {
+    let mut args = pico_args::Arguments::from_env();
+    Args {
+        alloc: args.contains("--alloc"),
+    }
+}
+
 fn main() {
+    let args = parse_args();
+
     let total: f64 = (1..=25)
         .map(|day| {
             let day = format!("{:02}", day);
 
-            let cmd = Command::new("cargo")
-                .args(["run", "--release", "--bin", &day])
-                .output()
-                .unwrap();
+            let mut cmd_args = vec!["run", "--release", "--bin", &day];
+            if args.alloc {
+                cmd_args.extend(["--features", "alloc-stats"]);
+            }
+
+            let cmd = Command::new("cargo").args(cmd_args).output().unwrap();
 
             println!("----------");
             println!("{}| Day {} |{}", ANSI_BOLD, day, ANSI_RESET);